@@ -1,14 +1,22 @@
 // Copyright 2023 CeresDB Project Authors. Licensed under Apache-2.0.
 
-use std::{fmt::Display, path::Path};
+use std::{fmt::Display, path::Path, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use futures::executor::block_on;
 use sqlness::{
-    database_impl::{mysql::MysqlDatabase, postgresql::PostgresqlDatabase},
-    ConfigBuilder, Database, DatabaseConfig, DatabaseConfigBuilder, EnvController, QueryContext,
-    Runner,
+    database_impl::{
+        http::{HttpConfig, HttpConfigBuilder, HttpDatabase, ResponseMapping},
+        mysql::MysqlDatabase,
+        postgresql::PostgresqlDatabase,
+        sqlite::{SqliteDatabase, IN_MEMORY},
+    },
+    report::{json::JsonLinesWriter, junit::JunitWriter, tap::TapWriter, ReportWriterRef},
+    splitter::SqlSplitter,
+    ConfigBuilder, Database, DatabaseConfig, DatabaseConfigBuilder, EnvController,
+    MissingResultPolicy, NewResultOutput, QueryContext, QueryResult, ResultMode, Runner, TlsConfig,
+    TlsMode,
 };
 
 #[derive(Parser, Debug)]
@@ -19,13 +27,41 @@ struct Args {
     #[clap(short, long, required(true))]
     case_dir: String,
 
-    /// IP of database to test against
-    #[clap(short, long, required(true))]
-    ip: String,
+    /// IP of database to test against, required by mysql and postgresql unless
+    /// socket is set
+    #[clap(short, long)]
+    ip: Option<String>,
 
-    /// Port of database to test against
-    #[clap(short, long, required(true))]
-    port: u16,
+    /// Port of database to test against, required by mysql and postgresql unless
+    /// socket is set
+    #[clap(short, long)]
+    port: Option<u16>,
+
+    /// Path of sqlite database file, use an in-memory database if not set
+    #[clap(long)]
+    db_path: Option<String>,
+
+    /// URL template of http database, e.g.
+    /// `http://127.0.0.1:8123/?query={{ query | urlencode }}`
+    #[clap(long)]
+    url: Option<String>,
+
+    /// HTTP method of http database
+    #[clap(long, default_value = "POST")]
+    method: String,
+
+    /// Extra header of http database in `Name: value` format, can be repeated
+    #[clap(long)]
+    header: Vec<String>,
+
+    /// Body template of http database
+    #[clap(long, default_value = "{{ query }}")]
+    body: String,
+
+    /// JSON path to map http response in `name=path` format, name is one of
+    /// error, rows, columns, types and affected_rows. Can be repeated
+    #[clap(long)]
+    map: Vec<String>,
 
     /// User of database to test against
     #[clap(short, long, required(false))]
@@ -39,10 +75,74 @@ struct Args {
     #[clap(short, long)]
     db: Option<String>,
 
+    /// Unix socket of database to test against, used instead of ip and port.
+    /// PostgreSQL takes the directory containing it
+    #[clap(long)]
+    socket: Option<String>,
+
+    /// Seconds to wait for connecting to database
+    #[clap(long)]
+    connect_timeout: Option<u64>,
+
+    /// Statement executed on each new connection, can be repeated
+    #[clap(long)]
+    init_statement: Vec<String>,
+
+    /// Driver specific parameter in `key=value` format, can be repeated
+    #[clap(long)]
+    param: Vec<String>,
+
+    /// Whether to use TLS, and how the server is verified
+    #[clap(long)]
+    #[arg(value_enum, default_value_t)]
+    tls_mode: Tls,
+
+    /// PEM file of CA certificates to verify the server
+    #[clap(long)]
+    tls_ca: Option<String>,
+
+    /// Client certificate, PEM file for PostgreSQL, PKCS #12 archive for MySQL
+    #[clap(long)]
+    tls_cert: Option<String>,
+
+    /// PEM file of client private key, only needed by PostgreSQL
+    #[clap(long)]
+    tls_key: Option<String>,
+
     /// Which DBMS to test against
     #[clap(short('t'), long("type"))]
     #[arg(value_enum, default_value_t)]
     db_type: DBType,
+
+    /// How result files are handled
+    #[clap(long)]
+    #[arg(value_enum, default_value_t)]
+    mode: Mode,
+
+    /// Under `check` mode, write new results to this directory
+    #[clap(long, conflicts_with = "new_result_sibling")]
+    new_result_dir: Option<String>,
+
+    /// Under `check` mode, write new results next to result files
+    #[clap(long)]
+    new_result_sibling: bool,
+
+    /// What to do with cases whose result file doesn't exist
+    #[clap(long)]
+    #[arg(value_enum, default_value_t)]
+    missing_result: MissingResult,
+
+    /// Write report in JUnit XML format to this path
+    #[clap(long)]
+    junit: Option<String>,
+
+    /// Write report in JSON Lines format to this path
+    #[clap(long)]
+    json: Option<String>,
+
+    /// Write report in TAP format to this path
+    #[clap(long)]
+    tap: Option<String>,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, Default, Copy)]
@@ -50,6 +150,64 @@ enum DBType {
     #[default]
     Mysql,
     Postgresql,
+    Sqlite,
+    Http,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, Default, Copy)]
+enum Mode {
+    #[default]
+    Overwrite,
+    Check,
+    Bless,
+}
+
+impl From<Mode> for ResultMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Overwrite => ResultMode::Overwrite,
+            Mode::Check => ResultMode::Check,
+            Mode::Bless => ResultMode::Bless,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, Default, Copy)]
+enum Tls {
+    #[default]
+    Disable,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl From<Tls> for TlsMode {
+    fn from(tls: Tls) -> Self {
+        match tls {
+            Tls::Disable => TlsMode::Disable,
+            Tls::Require => TlsMode::Require,
+            Tls::VerifyCa => TlsMode::VerifyCa,
+            Tls::VerifyFull => TlsMode::VerifyFull,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, Default, Copy)]
+enum MissingResult {
+    #[default]
+    Create,
+    Fail,
+    Warn,
+}
+
+impl From<MissingResult> for MissingResultPolicy {
+    fn from(policy: MissingResult) -> Self {
+        match policy {
+            MissingResult::Create => MissingResultPolicy::Create,
+            MissingResult::Fail => MissingResultPolicy::Fail,
+            MissingResult::Warn => MissingResultPolicy::Warn,
+        }
+    }
 }
 
 struct DBProxy {
@@ -61,29 +219,50 @@ impl Database for DBProxy {
     async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
         self.database.query(context, query).await
     }
+
+    async fn query_result(&self, context: QueryContext, query: String) -> QueryResult {
+        self.database.query_result(context, query).await
+    }
 }
 
 impl DBProxy {
-    pub fn new(db_config: DatabaseConfig, db_type: DBType) -> Self {
-        let database: Box<dyn Database + Sync + Send> = match db_type {
-            DBType::Mysql => Box::new(MysqlDatabase::try_new(db_config).expect("build mysql db")),
-            DBType::Postgresql => {
+    pub fn new(target: Target) -> Self {
+        let database: Box<dyn Database + Sync + Send> = match target {
+            Target::Mysql(db_config) => {
+                Box::new(MysqlDatabase::try_new(db_config).expect("build mysql db"))
+            }
+            Target::Postgresql(db_config) => {
                 Box::new(PostgresqlDatabase::try_new(&db_config).expect("build postgresql db"))
             }
+            Target::Sqlite(path) => {
+                Box::new(SqliteDatabase::try_new(path).expect("build sqlite db"))
+            }
+            Target::Http(http_config) => {
+                Box::new(HttpDatabase::try_new(http_config).expect("build http db"))
+            }
         };
 
         DBProxy { database }
     }
 }
 
+/// Where the database to test against is.
+#[derive(Clone)]
+enum Target {
+    Mysql(DatabaseConfig),
+    Postgresql(DatabaseConfig),
+    /// Path of sqlite database file.
+    Sqlite(String),
+    Http(HttpConfig),
+}
+
 struct CliController {
-    db_config: DatabaseConfig,
-    db_type: DBType,
+    target: Target,
 }
 
 impl CliController {
-    fn new(db_config: DatabaseConfig, db_type: DBType) -> Self {
-        Self { db_config, db_type }
+    fn new(target: Target) -> Self {
+        Self { target }
     }
 }
 
@@ -91,32 +270,161 @@ impl CliController {
 impl EnvController for CliController {
     type DB = DBProxy;
 
-    async fn start(&self, _env: &str, _config: Option<&Path>) -> Self::DB {
-        DBProxy::new(self.db_config.clone(), self.db_type)
+    /// Fields of [`DatabaseConfig`] can be overridden by environment's config
+    /// file.
+    async fn start(&self, _env: &str, config: Option<&Path>) -> Self::DB {
+        let mut target = self.target.clone();
+        if let (Target::Mysql(db_config) | Target::Postgresql(db_config), Some(path)) =
+            (&mut target, config)
+        {
+            db_config
+                .update_from_file(path)
+                .expect("read database config");
+        }
+
+        DBProxy::new(target)
     }
 
     async fn stop(&self, _env: &str, _db: Self::DB) {}
 }
 
+/// Config of database server, exit if `--ip` or `--port` is missing.
+fn server_config(args: &Args) -> DatabaseConfig {
+    let (ip, port) = match (&args.ip, args.port, &args.socket) {
+        (Some(ip), Some(port), _) => (ip.clone(), port),
+        // Port is also needed by PostgreSQL to locate the socket file.
+        (ip, port, Some(_)) => (ip.clone().unwrap_or_default(), port.unwrap_or(5432)),
+        _ => Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--ip and --port, or --socket are required for mysql and postgresql",
+            )
+            .exit(),
+    };
+    let params = args
+        .param
+        .iter()
+        .map(|param| match param.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => Args::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("Invalid param `{param}`, expect `key=value`"),
+                )
+                .exit(),
+        })
+        .collect();
+    let tls = TlsConfig {
+        mode: args.tls_mode.into(),
+        ca_file: args.tls_ca.clone().map(Into::into),
+        cert_file: args.tls_cert.clone().map(Into::into),
+        key_file: args.tls_key.clone().map(Into::into),
+    };
+
+    DatabaseConfigBuilder::default()
+        .ip_or_host(ip)
+        .tcp_port(port)
+        .user(args.user.clone())
+        .pass(args.password.clone())
+        .db_name(args.db.clone())
+        .socket(args.socket.clone().map(Into::into))
+        .connect_timeout(args.connect_timeout.map(Duration::from_secs))
+        .init_statements(args.init_statement.clone())
+        .params(params)
+        .tls(tls)
+        .build()
+        .expect("build db config")
+}
+
+/// Config of http database, exit if any argument is invalid.
+fn http_config(args: &Args) -> HttpConfig {
+    let exit = |msg: String| -> ! {
+        Args::command()
+            .error(ErrorKind::ValueValidation, msg)
+            .exit()
+    };
+    let Some(url) = &args.url else {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--url is required for http",
+            )
+            .exit()
+    };
+
+    let headers = args
+        .header
+        .iter()
+        .map(|header| match header.split_once(':') {
+            Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+            None => exit(format!("Invalid header `{header}`, expect `Name: value`")),
+        })
+        .collect();
+    let mut mapping = ResponseMapping::default();
+    for map in &args.map {
+        let Some((name, path)) = map.split_once('=') else {
+            exit(format!("Invalid map `{map}`, expect `name=path`"))
+        };
+        if let Err(e) = mapping.set(name.trim(), path.trim()) {
+            exit(e.to_string())
+        }
+    }
+
+    HttpConfigBuilder::default()
+        .url(url.clone())
+        .method(args.method.clone())
+        .headers(headers)
+        .body(args.body.clone())
+        .mapping(mapping)
+        .build()
+        .expect("build http config")
+}
+
 fn main() {
     println!("Begin run tests...");
     let args = Args::parse();
-    let db_config = DatabaseConfigBuilder::default()
-        .ip_or_host(args.ip)
-        .tcp_port(args.port)
-        .user(args.user)
-        .pass(args.password)
-        .db_name(args.db)
-        .build()
-        .expect("build db config");
+    let target = match args.db_type {
+        DBType::Mysql => Target::Mysql(server_config(&args)),
+        DBType::Postgresql => Target::Postgresql(server_config(&args)),
+        DBType::Sqlite => Target::Sqlite(args.db_path.clone().unwrap_or(IN_MEMORY.to_string())),
+        DBType::Http => Target::Http(http_config(&args)),
+    };
 
-    let config = ConfigBuilder::default()
+    let mut report_writers: Vec<ReportWriterRef> = Vec::new();
+    if let Some(path) = args.junit {
+        report_writers.push(Arc::new(JunitWriter::new(path)));
+    }
+    if let Some(path) = args.json {
+        report_writers.push(Arc::new(JsonLinesWriter::new(path)));
+    }
+    if let Some(path) = args.tap {
+        report_writers.push(Arc::new(TapWriter::new(path)));
+    }
+
+    let new_result_output = match args.new_result_dir {
+        Some(dir) => NewResultOutput::Dir(dir),
+        None if args.new_result_sibling => NewResultOutput::Sibling,
+        None => NewResultOutput::Discard,
+    };
+
+    let mut config = ConfigBuilder::default()
         .case_dir(args.case_dir)
+        .result_mode(args.mode.into())
+        .new_result_output(new_result_output)
+        .missing_result_policy(args.missing_result.into())
+        .report_writers(report_writers)
         .build()
         .expect("build config");
+    // Backslashes escape in MySQL strings.
+    if let DBType::Mysql = args.db_type {
+        config.splitters.insert(
+            config.test_case_extension.clone(),
+            SqlSplitter::MYSQL_NAME.to_string(),
+        );
+    }
 
     block_on(async {
-        let ctrl = CliController::new(db_config, args.db_type);
+        let ctrl = CliController::new(target);
         let runner = Runner::new(config, ctrl);
         runner.run().await.expect("run testcase")
     });
@@ -1,84 +1,125 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
-use std::{
-    collections::HashMap,
-    fmt::Display,
-    fs::File,
-    io::{BufRead, BufReader, Write},
-    path::Path,
-};
+use std::{collections::HashMap, fmt::Display, io::Write, path::Path, time::Duration};
 
 use crate::{
-    config::Config,
+    config::{Config, EnvConfig, UnterminatedStatement},
+    database::QueryResult,
     error::Result,
-    interceptor::{InterceptorRef, Registry},
-    Database, SqlnessError,
+    interceptor::{expect_error::ExpectedError, retry::RetryPolicy, InterceptorRef, Registry},
+    splitter::{Segment, SplitterRef, SqlSplitter, Statement},
+    timer, Database, OutputFormat, SqlnessError,
 };
 
-const COMMENT_PREFIX: &str = "--";
-const QUERY_DELIMITER: char = ';';
-
 pub(crate) struct TestCase {
     name: String,
     queries: Vec<Query>,
+    /// Context every query starts with, like the default timeout, which may
+    /// be overridden by interceptors.
+    default_context: QueryContext,
 }
 
 impl TestCase {
-    pub(crate) fn from_file<P: AsRef<Path>>(path: P, cfg: &Config) -> Result<Self> {
-        let file = File::open(path.as_ref()).map_err(|e| SqlnessError::ReadPath {
-            source: e,
+    pub(crate) fn from_file<P: AsRef<Path>>(
+        path: P,
+        cfg: &Config,
+        env_config: &EnvConfig,
+        splitter: &SplitterRef,
+    ) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path.as_ref()).map_err(|e| SqlnessError::ReadPath {
+                source: e,
+                path: path.as_ref().to_path_buf(),
+            })?;
+        // The prefix is written for SQL, use the comment prefix of this splitter.
+        let interceptor_prefix = match cfg
+            .interceptor_prefix
+            .strip_prefix(SqlSplitter::COMMENT_PREFIX)
+        {
+            Some(keyword) => format!("{}{keyword}", splitter.comment_prefix()),
+            None => cfg.interceptor_prefix.clone(),
+        };
+        let new_query = || Query {
+            delimiter: splitter.delimiter().map(String::from),
+            ..Query::with_interceptor_factories(cfg.interceptor_registry.clone())
+        };
+
+        let located = |line, e| SqlnessError::ParseCase {
+            source: Box::new(e),
             path: path.as_ref().to_path_buf(),
-        })?;
+            line,
+        };
 
         let mut queries = vec![];
-        let mut query = Query::with_interceptor_factories(cfg.interceptor_registry.clone());
-
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            let line = line?;
-
-            // record comment
-            if line.starts_with(COMMENT_PREFIX) {
-                query.push_comment(line.clone());
-
-                // intercept command start with INTERCEPTOR_PREFIX
-                if line.starts_with(&cfg.interceptor_prefix) {
-                    query.push_interceptor(&cfg.interceptor_prefix, line)?;
+        let mut query = new_query();
+        for segment in splitter.split(&content) {
+            match segment {
+                Segment::Comment { line, text } => {
+                    // intercept command start with INTERCEPTOR_PREFIX
+                    if text.starts_with(&interceptor_prefix) {
+                        query
+                            .push_interceptor(&interceptor_prefix, text.clone())
+                            .map_err(|e| located(line, e))?;
+                    }
+                    // record comment
+                    query.push_comment(text);
+                }
+                Segment::Query(statement) => {
+                    if !statement.terminated
+                        && cfg.unterminated_statement == UnterminatedStatement::Error
+                    {
+                        return Err(located(statement.line, SqlnessError::UnterminatedStatement));
+                    }
+                    query.append_statement(statement);
+                    queries.push(query);
+                    query = new_query();
                 }
-                continue;
-            }
-
-            // ignore empty line
-            if line.is_empty() {
-                continue;
-            }
-
-            query.append_query_line(&line);
-
-            // SQL statement ends with ';'
-            if line.ends_with(QUERY_DELIMITER) {
-                queries.push(query);
-                query = Query::with_interceptor_factories(cfg.interceptor_registry.clone());
-            } else {
-                query.append_query_line("\n");
             }
         }
 
         Ok(Self {
             name: path.as_ref().to_str().unwrap().to_string(),
             queries,
+            default_context: QueryContext {
+                timeout: cfg.query_timeout,
+                format: env_config.format,
+                ..Default::default()
+            },
         })
     }
 
-    pub(crate) async fn execute<W>(&mut self, db: &dyn Database, writer: &mut W) -> Result<()>
+    /// Execute all queries and write their results to `writer`.
+    ///
+    /// `expected` is the content of old result file if exists, which is used
+    /// by interceptors like `RETRY` to know the expected result of a query.
+    ///
+    /// Return messages of queries that make this case fail regardless of its
+    /// result, like timed out queries.
+    pub(crate) async fn execute<W>(
+        &mut self,
+        db: &dyn Database,
+        expected: Option<&str>,
+        writer: &mut W,
+    ) -> Result<Vec<String>>
     where
         W: Write,
     {
+        let mut failures = Vec::new();
+        let mut output = Vec::new();
         for query in &mut self.queries {
-            query.execute(db, writer).await?;
+            query
+                .execute(
+                    db,
+                    &self.default_context,
+                    expected,
+                    &mut output,
+                    &mut failures,
+                )
+                .await?;
         }
+        writer.write_all(&output)?;
 
-        Ok(())
+        Ok(failures)
     }
 }
 
@@ -88,10 +129,19 @@ impl Display for TestCase {
     }
 }
 
-/// A String-to-String map used as query context.
+/// A String-to-String map used as query context, along with execution
+/// options set by interceptors.
 #[derive(Default, Debug, Clone)]
 pub struct QueryContext {
     pub context: HashMap<String, String>,
+    /// Abort the query if it doesn't finish in this duration.
+    pub timeout: Option<Duration>,
+    /// Re-run the query until its result is the same as expected.
+    pub retry: Option<RetryPolicy>,
+    /// The query is expected to fail.
+    pub expect_error: Option<ExpectedError>,
+    /// How result sets are rendered.
+    pub format: OutputFormat,
 }
 
 #[derive(Default)]
@@ -103,6 +153,10 @@ struct Query {
     execute_query: Vec<String>,
     interceptor_registry: Registry,
     interceptors: Vec<InterceptorRef>,
+    /// Appended to SQLs not ending with it, see [`Splitter::delimiter`].
+    ///
+    /// [`Splitter::delimiter`]: crate::splitter::Splitter::delimiter
+    delimiter: Option<String>,
 }
 
 impl Query {
@@ -133,49 +187,141 @@ impl Query {
         self.comment_lines.push(comment_line);
     }
 
-    fn append_query_line(&mut self, line: &str) {
-        self.display_query.push(line.to_string());
-        self.execute_query.push(line.to_string());
+    fn append_statement(&mut self, statement: Statement) {
+        self.display_query.push(statement.display);
+        // Interceptors process the query line by line.
+        for (index, line) in statement.execute.split('\n').enumerate() {
+            if index > 0 {
+                self.execute_query.push("\n".to_string());
+            }
+            self.execute_query.push(line.to_string());
+        }
     }
 
-    async fn execute<W>(&mut self, db: &dyn Database, writer: &mut W) -> Result<()>
-    where
-        W: Write,
-    {
-        let context = self.before_execute_intercept().await;
+    /// Execute this query and append the result to `output`.
+    async fn execute(
+        &mut self,
+        db: &dyn Database,
+        default_context: &QueryContext,
+        expected: Option<&str>,
+        output: &mut Vec<u8>,
+        failures: &mut Vec<String>,
+    ) -> Result<()> {
+        let context = self.before_execute_intercept(default_context).await;
         for comment in &self.comment_lines {
-            writer.write_all(comment.as_bytes())?;
-            writer.write_all("\n".as_bytes())?;
+            output.write_all(comment.as_bytes())?;
+            output.write_all("\n".as_bytes())?;
         }
         for comment in &self.display_query {
-            writer.write_all(comment.as_bytes())?;
+            output.write_all(comment.as_bytes())?;
         }
-        writer.write_all("\n\n".as_bytes())?;
+        output.write_all("\n\n".as_bytes())?;
 
         let sql = self.concat_query_lines();
         // An intercetor may generate multiple SQLs, so we need to split them.
         for sql in sql.split(crate::interceptor::template::DELIMITER) {
             if !sql.trim().is_empty() {
-                let sql = if sql.ends_with(QUERY_DELIMITER) {
-                    sql.to_string()
-                } else {
-                    format!("{sql};")
+                let sql = match &self.delimiter {
+                    Some(delimiter) if !sql.ends_with(delimiter.as_str()) => {
+                        format!("{sql}{delimiter}")
+                    }
+                    _ => sql.to_string(),
                 };
-                let mut result = db.query(context.clone(), sql).await.to_string();
-                self.after_execute_intercept(&mut result).await;
-                self.write_result(writer, result)?;
+
+                // The expected result of this SQL follows what has been written,
+                // only known when the output so far is the same as expected.
+                let expected_result = expected.and_then(|expected| {
+                    expected
+                        .as_bytes()
+                        .starts_with(output)
+                        .then(|| expected.get(output.len()..))
+                        .flatten()
+                });
+                let mut attempt = 1;
+                let (result, failure) = loop {
+                    let (result, failure) = self.execute_once(db, &context, sql.clone()).await;
+                    let Some(retry) = &context.retry else {
+                        break (result, failure);
+                    };
+                    let Some(expected_result) = expected_result else {
+                        break (result, failure);
+                    };
+                    let is_expected = failure.is_none()
+                        && expected_result
+                            .strip_prefix(result.as_str())
+                            .is_some_and(|rest| rest.starts_with("\n\n"));
+                    if is_expected || attempt >= retry.max_attempts {
+                        break (result, failure);
+                    }
+                    attempt += 1;
+                    timer::sleep(retry.interval).await;
+                };
+
+                if let Some(failure) = failure {
+                    failures.push(format!("{failure}: {}", self.display_query.concat()));
+                }
+                self.write_result(output, result)?;
             }
         }
 
         Ok(())
     }
 
+    /// Execute one SQL and run post-execution interceptors on its result.
+    ///
+    /// Return the result to be written, and why this SQL makes the case fail
+    /// if any, like timed out or not failed as expected.
+    async fn execute_once(
+        &self,
+        db: &dyn Database,
+        context: &QueryContext,
+        sql: String,
+    ) -> (String, Option<String>) {
+        let query = db.query_result(context.clone(), sql);
+        let result = match context.timeout {
+            Some(timeout) => match timer::timeout(timeout, query).await {
+                Some(result) => result,
+                None => {
+                    // Don't intercept the error message so it's always the same.
+                    let message = format!("Query timed out after {timeout:?}");
+                    return (message.clone(), Some(message));
+                }
+            },
+            None => query.await,
+        };
+
+        let (mut result, failure) = match (&context.expect_error, result) {
+            (None, result) => (context.format.render_result(&result), None),
+            (Some(expected), QueryResult::Error(error)) => {
+                let error = error.to_string();
+                match &expected.pattern {
+                    Some(pattern) if !pattern.is_match(&error) => {
+                        (error, Some(format!("Error doesn't match `{pattern}`")))
+                    }
+                    // Record a fixed message, so the result doesn't depend on
+                    // the exact error text.
+                    Some(pattern) => (format!("Expected error occurred, matches: {pattern}"), None),
+                    None => ("Expected error occurred".to_string(), None),
+                }
+            }
+            (Some(_), result) => (
+                context.format.render_result(&result),
+                Some("Expect error but query succeeded".to_string()),
+            ),
+        };
+        if context.expect_error.is_none() || failure.is_some() {
+            self.after_execute_intercept(&mut result).await;
+        }
+
+        (result, failure)
+    }
+
     /// Run pre-execution interceptors.
     ///
     /// Interceptors may change either the query to be displayed or the query to be executed,
     /// so we need to return the query to caller.
-    async fn before_execute_intercept(&mut self) -> QueryContext {
-        let mut context = QueryContext::default();
+    async fn before_execute_intercept(&mut self, default_context: &QueryContext) -> QueryContext {
+        let mut context = default_context.clone();
 
         for interceptor in &self.interceptors {
             interceptor
@@ -186,7 +332,7 @@ impl Query {
         context
     }
 
-    async fn after_execute_intercept(&mut self, result: &mut String) {
+    async fn after_execute_intercept(&self, result: &mut String) {
         for interceptor in &self.interceptors {
             interceptor.after_execute_async(result).await;
         }
@@ -212,3 +358,256 @@ impl Query {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        splitter::{BlankLineSplitter, SqlSplitter},
+        ConfigBuilder,
+    };
+
+    /// Answers queries by `handler`, which is called with the query and how
+    /// many times it has been executed. Queries starting with `HANG` never
+    /// finish.
+    struct StubDatabase<F> {
+        handler: F,
+        attempts: Mutex<HashMap<String, usize>>,
+    }
+
+    impl<F: Fn(&str, usize) -> QueryResult + Sync> StubDatabase<F> {
+        fn new(handler: F) -> Self {
+            Self {
+                handler,
+                attempts: Mutex::new(HashMap::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<F: Fn(&str, usize) -> QueryResult + Sync> Database for StubDatabase<F> {
+        async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+            Box::new(self.query_result(context, query).await)
+        }
+
+        async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+            if query.starts_with("HANG") {
+                std::future::pending::<()>().await;
+            }
+            let attempt = {
+                let mut attempts = self.attempts.lock().unwrap();
+                let attempt = attempts.entry(query.clone()).or_default();
+                *attempt += 1;
+                *attempt
+            };
+            (self.handler)(&query, attempt)
+        }
+    }
+
+    /// Parse `content` as a `.sql` case file.
+    fn parse_case(content: &str, config: &Config) -> TestCase {
+        let dir = std::env::temp_dir().join(format!(
+            "sqlness-case-parse-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("case.sql");
+        std::fs::write(&path, content).unwrap();
+        let splitter: SplitterRef = Arc::new(SqlSplitter::new());
+        let case = TestCase::from_file(&path, config, &EnvConfig::default(), &splitter).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        case
+    }
+
+    /// Execute `case`, return its output and failures.
+    async fn execute_case(
+        case: &mut TestCase,
+        db: &dyn Database,
+        expected: Option<&str>,
+    ) -> (String, Vec<String>) {
+        let mut output = Vec::new();
+        let failures = case.execute(db, expected, &mut output).await.unwrap();
+        (String::from_utf8(output).unwrap(), failures)
+    }
+
+    #[tokio::test]
+    async fn timeout_query() {
+        let config = ConfigBuilder::default()
+            .case_dir(String::new())
+            .query_timeout(Some(Duration::from_secs(30)))
+            .build()
+            .unwrap();
+        let mut case = parse_case("-- SQLNESS TIMEOUT 50ms\nHANG;\n\nSELECT 1;\n", &config);
+        let db = StubDatabase::new(|_, _| QueryResult::AffectedRows(1));
+
+        let (output, failures) = execute_case(&mut case, &db, None).await;
+        assert_eq!(
+            output,
+            "-- SQLNESS TIMEOUT 50ms\nHANG;\n\nQuery timed out after 50ms\n\nSELECT 1;\n\naffected_rows: 1\n\n"
+        );
+        assert_eq!(failures, vec!["Query timed out after 50ms: HANG;"]);
+    }
+
+    fn default_config() -> Config {
+        ConfigBuilder::default()
+            .case_dir(String::new())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retry_until_expected() {
+        let content = "-- SQLNESS REPLACE \\d+ N\n-- SQLNESS RETRY 5 10ms\nSELECT n;\n";
+        let expected = format!("{content}\nready N\n\n");
+        // Only the last attempt is ready, and the result matches after `REPLACE`.
+        let db = StubDatabase::new(|_, attempt| {
+            let state = if attempt < 3 { "pending" } else { "ready" };
+            QueryResult::Output(Box::new(format!("{state} {attempt}")))
+        });
+
+        let mut case = parse_case(content, &default_config());
+        let (output, failures) = execute_case(&mut case, &db, Some(&expected)).await;
+        assert_eq!(output, expected);
+        assert!(failures.is_empty());
+        assert_eq!(db.attempts.lock().unwrap()["SELECT n;"], 3);
+
+        // Give up after max attempts, with the last result recorded.
+        let db = StubDatabase::new(|_, attempt| QueryResult::Output(Box::new(attempt)));
+        let content = "-- SQLNESS RETRY 3 10ms\nSELECT n;\n";
+        let mut case = parse_case(content, &default_config());
+        let expected = format!("{content}\n9\n\n");
+        let (output, _) = execute_case(&mut case, &db, Some(&expected)).await;
+        assert_eq!(output, format!("{content}\n3\n\n"));
+        assert_eq!(db.attempts.lock().unwrap()["SELECT n;"], 3);
+    }
+
+    #[tokio::test]
+    async fn expect_error() {
+        let db = StubDatabase::new(|query, _| {
+            if query.starts_with("SELECT") {
+                QueryResult::AffectedRows(0)
+            } else {
+                QueryResult::Error(Box::new("Table t not found, code: 1146".to_string()))
+            }
+        });
+        let content = "-- SQLNESS EXPECT_ERROR\nSELECT 1;\n\n\
+            -- SQLNESS EXPECT_ERROR\nDROP TABLE t;\n\n\
+            -- SQLNESS EXPECT_ERROR (?i)table .* not found\nDROP TABLE t;\n\n\
+            -- SQLNESS EXPECT_ERROR already exists\nDROP TABLE t;\n";
+
+        let mut case = parse_case(content, &default_config());
+        let (output, failures) = execute_case(&mut case, &db, None).await;
+        assert_eq!(
+            output,
+            "-- SQLNESS EXPECT_ERROR\nSELECT 1;\n\naffected_rows: 0\n\n\
+             -- SQLNESS EXPECT_ERROR\nDROP TABLE t;\n\nExpected error occurred\n\n\
+             -- SQLNESS EXPECT_ERROR (?i)table .* not found\nDROP TABLE t;\n\n\
+             Expected error occurred, matches: (?i)table .* not found\n\n\
+             -- SQLNESS EXPECT_ERROR already exists\nDROP TABLE t;\n\n\
+             Table t not found, code: 1146\n\n"
+        );
+        assert_eq!(
+            failures,
+            [
+                "Expect error but query succeeded: SELECT 1;",
+                "Error doesn't match `already exists`: DROP TABLE t;",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_only_when_expected_is_known() {
+        let content = "SELECT 1;\n\n-- SQLNESS RETRY 3 10ms\nSELECT n;\n";
+        let db = StubDatabase::new(|_, attempt| QueryResult::Output(Box::new(attempt)));
+
+        // Result of the first query already differs.
+        let expected = "SELECT 1;\n\n2\n\n-- SQLNESS RETRY 3 10ms\nSELECT n;\n\n3\n\n";
+        let mut case = parse_case(content, &default_config());
+        let (output, _) = execute_case(&mut case, &db, Some(expected)).await;
+        assert_eq!(
+            output,
+            "SELECT 1;\n\n1\n\n-- SQLNESS RETRY 3 10ms\nSELECT n;\n\n1\n\n"
+        );
+        assert_eq!(db.attempts.lock().unwrap()["SELECT n;"], 1);
+
+        // No result file.
+        let mut case = parse_case(content, &default_config());
+        execute_case(&mut case, &db, None).await;
+        assert_eq!(db.attempts.lock().unwrap()["SELECT n;"], 2);
+    }
+
+    #[test]
+    fn unterminated_statement() {
+        let dir = std::env::temp_dir().join(format!("sqlness-case-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("unterminated.sql");
+        std::fs::write(&path, "SELECT 1;\n\nSELECT\n  2\n").unwrap();
+        let splitter: SplitterRef = Arc::new(SqlSplitter::new());
+        let mut config = ConfigBuilder::default()
+            .case_dir(dir.display().to_string())
+            .build()
+            .unwrap();
+
+        let err = TestCase::from_file(&path, &config, &EnvConfig::default(), &splitter)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("{}:3: Unterminated statement.", path.display())
+        );
+
+        config.unterminated_statement = UnterminatedStatement::Execute;
+        let case = TestCase::from_file(&path, &config, &EnvConfig::default(), &splitter).unwrap();
+        assert_eq!(case.queries.len(), 2);
+        assert_eq!(case.queries[1].concat_query_lines(), "SELECT\n  2");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locate_interceptor_error() {
+        let dir = std::env::temp_dir().join(format!("sqlness-case-err-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = ConfigBuilder::default()
+            .case_dir(dir.display().to_string())
+            .build()
+            .unwrap();
+        let cases: [(&str, SplitterRef, &str, &str); 3] = [
+            (
+                "unknown.sql",
+                Arc::new(SqlSplitter::new()),
+                "SELECT 1;\n-- SQLNESS UNKNOWN a\nSELECT 2;\n",
+                "2: Unknown interceptor prefix, value:UNKNOWN.",
+            ),
+            (
+                "invalid.sql",
+                Arc::new(SqlSplitter::new()),
+                "-- SQLNESS ARG a=1\n-- SQLNESS TIMEOUT soon\nSELECT 1;\n",
+                "2: Invalid interceptor context, prefix:TIMEOUT",
+            ),
+            (
+                "unknown.promql",
+                Arc::new(BlankLineSplitter::new("#")),
+                "up\n\n# SQLNESS UNKNOWN\nup\n",
+                "3: Unknown interceptor prefix, value:UNKNOWN.",
+            ),
+        ];
+        for (name, splitter, content, message) in cases {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            let err = TestCase::from_file(&path, &config, &EnvConfig::default(), &splitter)
+                .err()
+                .unwrap();
+            let expected = format!("{}:{message}", path.display());
+            assert!(err.to_string().starts_with(&expected), "{err}");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,15 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::{Result, SqlnessError};
 use crate::interceptor::Registry;
+use crate::report::ReportWriterRef;
+use crate::splitter::{self, BlankLineSplitter, SqlSplitter};
+use crate::OutputFormat;
 use derive_builder::Builder;
 
 /// Configurations of [`Runner`].
@@ -16,6 +25,9 @@ pub struct Config {
     #[builder(default = "Config::default_result_extension()")]
     pub result_extension: String,
     /// Default value: `-- SQLNESS`
+    ///
+    /// For splitters with other comment prefixes, the leading `--` is replaced
+    /// by theirs, e.g. `# SQLNESS` for `promql`.
     #[builder(default = "Config::default_interceptor_prefix()")]
     pub interceptor_prefix: String,
     /// Default value: `config.toml`
@@ -35,9 +47,70 @@ pub struct Config {
     /// Defaults to "true" (follow symbolic links).
     #[builder(default = "Config::default_follow_links()")]
     pub follow_links: bool,
+    /// How many cases of one environment are run concurrently. Queries inside
+    /// one case are always executed in order.
+    /// Default value: `1` (run cases one after another)
+    #[builder(default = "Config::default_parallelism()")]
+    pub parallelism: usize,
+    /// How many environments are run concurrently. Only set this when
+    /// environments are isolated from each other.
+    /// Default value: `1` (run environments one after another)
+    #[builder(default = "Config::default_env_parallelism()")]
+    pub env_parallelism: usize,
+    /// How `.result` files are handled after cases are executed.
+    /// Default value: [`ResultMode::Overwrite`]
+    #[builder(default = "Config::default_result_mode()")]
+    pub result_mode: ResultMode,
+    /// Where to write the new result under [`ResultMode::Check`].
+    /// Default value: [`NewResultOutput::Discard`]
+    #[builder(default = "Config::default_new_result_output()")]
+    pub new_result_output: NewResultOutput,
+    /// What to do with cases whose `.result` file doesn't exist.
+    /// Default value: [`MissingResultPolicy::Create`]
+    #[builder(default = "Config::default_missing_result_policy()")]
+    pub missing_result_policy: MissingResultPolicy,
+    /// What to do with the last statement of a case file missing its
+    /// delimiter, e.g. `;` in `.sql` files.
+    /// Default value: [`UnterminatedStatement::Error`]
+    #[builder(default = "Config::default_unterminated_statement()")]
+    pub unterminated_statement: UnterminatedStatement,
+    /// Abort a query if it doesn't finish in this duration, may be overridden
+    /// by `TIMEOUT` interceptor. A timed out query makes its case fail.
+    ///
+    /// The query is abandoned by dropping its future, so it only works if the
+    /// future of [`Database::query_result`] yields while waiting. [`Database`]
+    /// implementations must not block, and should run blocking clients on
+    /// another thread like the built-in ones do.
+    /// Default value: `None` (no timeout)
+    ///
+    /// [`Database`]: crate::Database
+    /// [`Database::query_result`]: crate::Database::query_result
+    #[builder(default = "Config::default_query_timeout()")]
+    pub query_timeout: Option<Duration>,
     /// Interceptors used to pre-process input query and post-process query response
     #[builder(default = "Config::default_registry()")]
     pub interceptor_registry: Registry,
+    /// Splitters that can be referred to by name in [`Config::splitters`].
+    /// Default value: built-in ones, see [`splitter`]
+    #[builder(default = "Config::default_splitter_registry()")]
+    pub splitter_registry: splitter::Registry,
+    /// Names of splitters by file extension. Files with these extensions are
+    /// test cases as well as [`Config::test_case_extension`], which uses the
+    /// `sql` splitter unless set here. They can be overridden by the
+    /// `[sqlness.splitters]` table of each environment's config file.
+    ///
+    /// Cases of the same name but different extensions share one result file,
+    /// so they should be named differently.
+    ///
+    /// Default value: `{"promql": "promql"}`
+    #[builder(default = "Config::default_splitters()")]
+    pub splitters: HashMap<String, String>,
+    /// Writers to export the report after all environments finish, like
+    /// [`JunitWriter`]. Default: none
+    ///
+    /// [`JunitWriter`]: crate::report::junit::JunitWriter
+    #[builder(default = "Config::default_report_writers()")]
+    pub report_writers: Vec<ReportWriterRef>,
 }
 
 impl Config {
@@ -73,17 +146,539 @@ impl Config {
         true
     }
 
+    fn default_parallelism() -> usize {
+        1
+    }
+
+    fn default_env_parallelism() -> usize {
+        1
+    }
+
+    fn default_result_mode() -> ResultMode {
+        ResultMode::Overwrite
+    }
+
+    fn default_new_result_output() -> NewResultOutput {
+        NewResultOutput::Discard
+    }
+
+    fn default_missing_result_policy() -> MissingResultPolicy {
+        MissingResultPolicy::Create
+    }
+
+    fn default_unterminated_statement() -> UnterminatedStatement {
+        UnterminatedStatement::Error
+    }
+
+    fn default_query_timeout() -> Option<Duration> {
+        None
+    }
+
     fn default_registry() -> Registry {
         Registry::default()
     }
+
+    fn default_splitter_registry() -> splitter::Registry {
+        splitter::Registry::default()
+    }
+
+    fn default_splitters() -> HashMap<String, String> {
+        HashMap::from([(
+            BlankLineSplitter::PROMQL_NAME.to_string(),
+            BlankLineSplitter::PROMQL_NAME.to_string(),
+        )])
+    }
+
+    /// Names of splitters by file extension, with `overrides` of one
+    /// environment applied.
+    pub(crate) fn splitter_names(
+        &self,
+        overrides: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        let mut names = HashMap::from([(
+            self.test_case_extension.clone(),
+            SqlSplitter::NAME.to_string(),
+        )]);
+        names.extend(self.splitters.clone());
+        names.extend(overrides.clone());
+
+        names
+    }
+
+    fn default_report_writers() -> Vec<ReportWriterRef> {
+        Vec::new()
+    }
+}
+
+/// How `.result` files are handled after cases are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultMode {
+    /// Write the new result back to `.result` file, and fail the case if
+    /// it differs from the old one.
+    Overwrite,
+    /// Only compare the new result with `.result` file, which is never modified.
+    /// The new result is written to where [`NewResultOutput`] says.
+    Check,
+    /// Write the new result back to `.result` file, and never fail the case
+    /// due to difference. Used to regenerate expected results.
+    Bless,
+}
+
+/// Where to write the new result under [`ResultMode::Check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewResultOutput {
+    /// Don't write the new result.
+    Discard,
+    /// Write to a sibling file with `.new` appended, e.g. `select.result.new`,
+    /// only when the new result differs or `.result` file is missing. The
+    /// sibling file left by previous runs is removed if the result matches.
+    Sibling,
+    /// Write new results of all cases to the given directory, with the same
+    /// relative path as the `.result` file to case directory.
+    Dir(String),
+}
+
+/// What to do with cases whose `.result` file doesn't exist.
+///
+/// Under [`ResultMode::Check`], `Create` is treated as `Fail` since result
+/// files are never modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingResultPolicy {
+    /// Create the `.result` file from the new result, the case passes.
+    Create,
+    /// Don't create the `.result` file, the case fails.
+    Fail,
+    /// Don't create the `.result` file, print a warning and the case passes.
+    Warn,
+}
+
+/// What to do with the last statement of a case file missing its delimiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnterminatedStatement {
+    /// Fail the case with the file and line of the statement, so a missing
+    /// delimiter won't skip the statement silently.
+    Error,
+    /// Execute the statement as if it's terminated.
+    Execute,
+}
+
+/// Options of one environment that sqlness reads from the `[sqlness]` table
+/// of its config file, e.g.
+/// ``` toml
+/// [sqlness]
+/// format = "csv"
+///
+/// [sqlness.splitters]
+/// influxql = "promql"
+/// ```
+///
+/// Other tables are left to [`EnvController`].
+///
+/// [`EnvController`]: crate::EnvController
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EnvConfig {
+    /// Default style to render result sets of this environment.
+    pub(crate) format: OutputFormat,
+    /// Names of splitters by file extension, override [`Config::splitters`].
+    pub(crate) splitters: HashMap<String, String>,
+}
+
+impl EnvConfig {
+    pub(crate) const TABLE: &'static str = "sqlness";
+
+    pub(crate) fn from_file(path: &Path) -> Result<Self> {
+        let value = read_toml(path)?;
+        let invalid = |msg: String| SqlnessError::InvalidEnvConfig {
+            file: path.to_path_buf(),
+            msg,
+        };
+
+        let mut config = Self::default();
+        let Some(table) = value.get(Self::TABLE) else {
+            return Ok(config);
+        };
+        if let Some(format) = table.get("format") {
+            let format = format
+                .as_str()
+                .ok_or_else(|| invalid("`format` should be a string".to_string()))?;
+            config.format = format.parse().map_err(invalid)?;
+        }
+        if let Some(splitters) = table.get("splitters") {
+            let splitters = splitters.as_table().ok_or_else(|| {
+                invalid("`splitters` should be a table of extension to splitter name".to_string())
+            })?;
+            for (extension, name) in splitters {
+                let name = name.as_str().ok_or_else(|| {
+                    invalid(format!("splitter of `{extension}` should be a string"))
+                })?;
+                config.splitters.insert(extension.clone(), name.to_string());
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value> {
+    let content = std::fs::read_to_string(path).map_err(|e| SqlnessError::ReadPath {
+        source: e,
+        path: path.to_path_buf(),
+    })?;
+    content
+        .parse::<toml::Value>()
+        .map_err(|e| SqlnessError::ParseToml {
+            source: e,
+            file: path.to_path_buf(),
+        })
 }
 
 /// Config for DatabaseBuilder
 #[derive(Debug, Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct DatabaseConfig {
     pub ip_or_host: String,
     pub tcp_port: u16,
     pub user: Option<String>,
     pub pass: Option<String>,
     pub db_name: Option<String>,
+    /// Max number of connections of async backends, ignored by others.
+    /// Concurrent queries are spread over them, and as session states, like
+    /// `SET` variables and temporary tables, are per connection, keep it as 1
+    /// if cases rely on them. It must be positive.
+    /// Default value: `1`
+    #[builder(default = "DatabaseConfig::default_pool_size()")]
+    pub pool_size: usize,
+    /// Connection string for backends connecting through a driver manager,
+    /// e.g. `DSN=my_dsn` or `Driver={SQLite3};Database=test.db` for ODBC.
+    /// Those backends append `user` and `pass` to it if set, and ignore
+    /// other fields.
+    /// Default value: `None`
+    #[builder(default)]
+    pub connection_string: Option<String>,
+    /// Used by MySQL and PostgreSQL backends.
+    /// Default value: TLS disabled
+    #[builder(default)]
+    pub tls: TlsConfig,
+    /// Path of Unix domain socket, used instead of `ip_or_host` and
+    /// `tcp_port`. PostgreSQL backends take the directory containing it.
+    /// Default value: `None`
+    #[builder(default)]
+    pub socket: Option<PathBuf>,
+    /// Default value: `None`, wait as long as the driver does
+    #[builder(default)]
+    pub connect_timeout: Option<Duration>,
+    /// Statements executed on each new connection, e.g. to set time zone.
+    /// Default value: none
+    #[builder(default)]
+    pub init_statements: Vec<String>,
+    /// Driver specific parameters, passed as is. For example, MySQL backends
+    /// accept URL parameters like `compress`, and PostgreSQL backends accept
+    /// connection string parameters like `application_name`.
+    /// Default value: none
+    #[builder(default)]
+    pub params: Vec<(String, String)>,
+}
+
+impl DatabaseConfig {
+    /// Table in config file of environment to override fields.
+    pub const TABLE: &'static str = "database";
+
+    fn default_pool_size() -> usize {
+        1
+    }
+
+    /// Override fields by the [`DatabaseConfig::TABLE`] table in `path`, if
+    /// any, which usually is the config file of an environment. Keys are
+    /// field names, for example:
+    ///
+    /// ```toml
+    /// [database]
+    /// tcp_port = 3307
+    /// connect_timeout = "5s"
+    /// init_statements = ["SET time_zone = '+00:00'"]
+    /// params = { compress = "true" }
+    /// tls = { mode = "verify-full", ca_file = "ca.pem" }
+    /// ```
+    pub fn update_from_file(&mut self, path: &Path) -> Result<()> {
+        let value = read_toml(path)?;
+        let Some(table) = value.get(Self::TABLE) else {
+            return Ok(());
+        };
+        let table = table
+            .as_table()
+            .ok_or_else(|| invalid_value(path, Self::TABLE, "a table"))?;
+
+        let string = |key: &str, value: &toml::Value| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid_value(path, key, "a string"))
+        };
+        let integer = |key: &str, value: &toml::Value| {
+            value
+                .as_integer()
+                .ok_or_else(|| invalid_value(path, key, "an integer"))
+        };
+        for (key, value) in table {
+            match key.as_str() {
+                "ip_or_host" => self.ip_or_host = string(key, value)?,
+                "tcp_port" => {
+                    self.tcp_port = u16::try_from(integer(key, value)?)
+                        .map_err(|_| invalid_value(path, key, "a port number"))?
+                }
+                "user" => self.user = Some(string(key, value)?),
+                "pass" => self.pass = Some(string(key, value)?),
+                "db_name" => self.db_name = Some(string(key, value)?),
+                "pool_size" => {
+                    self.pool_size = usize::try_from(integer(key, value)?)
+                        .ok()
+                        .filter(|size| *size > 0)
+                        .ok_or_else(|| invalid_value(path, key, "a positive integer"))?
+                }
+                "connection_string" => self.connection_string = Some(string(key, value)?),
+                "socket" => self.socket = Some(PathBuf::from(string(key, value)?)),
+                "connect_timeout" => {
+                    let timeout = duration_str::parse(&string(key, value)?)
+                        .map_err(|_| invalid_value(path, key, "a duration like `5s`"))?;
+                    self.connect_timeout = Some(timeout);
+                }
+                "init_statements" => {
+                    self.init_statements = value
+                        .as_array()
+                        .ok_or_else(|| invalid_value(path, key, "an array of strings"))?
+                        .iter()
+                        .map(|statement| string(key, statement))
+                        .collect::<Result<_>>()?
+                }
+                "params" => {
+                    self.params = value
+                        .as_table()
+                        .ok_or_else(|| invalid_value(path, key, "a table"))?
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = match value {
+                                toml::Value::String(value) => value.clone(),
+                                value => value.to_string(),
+                            };
+                            (name.clone(), value)
+                        })
+                        .collect()
+                }
+                "tls" => {
+                    let tls = value
+                        .as_table()
+                        .ok_or_else(|| invalid_value(path, key, "a table"))?;
+                    for (key, value) in tls {
+                        match key.as_str() {
+                            "mode" => {
+                                self.tls.mode = string(key, value)?.parse().map_err(|msg| {
+                                    SqlnessError::InvalidEnvConfig {
+                                        file: path.to_path_buf(),
+                                        msg,
+                                    }
+                                })?
+                            }
+                            "ca_file" => self.tls.ca_file = Some(string(key, value)?.into()),
+                            "cert_file" => self.tls.cert_file = Some(string(key, value)?.into()),
+                            "key_file" => self.tls.key_file = Some(string(key, value)?.into()),
+                            _ => return Err(unknown_key(path, &format!("tls.{key}"))),
+                        }
+                    }
+                }
+                _ => return Err(unknown_key(path, key)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl DatabaseConfigBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        match self.pool_size {
+            Some(0) => Err("`pool_size` should be a positive integer".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn invalid_value(path: &Path, key: &str, expected: &str) -> SqlnessError {
+    SqlnessError::InvalidEnvConfig {
+        file: path.to_path_buf(),
+        msg: format!("`{key}` should be {expected}"),
+    }
+}
+
+fn unknown_key(path: &Path, key: &str) -> SqlnessError {
+    SqlnessError::InvalidEnvConfig {
+        file: path.to_path_buf(),
+        msg: format!("Unknown key `{key}` in `{}` table", DatabaseConfig::TABLE),
+    }
+}
+
+/// TLS settings of database connections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub mode: TlsMode,
+    /// PEM file of CA certificates to verify the server.
+    pub ca_file: Option<PathBuf>,
+    /// Client certificate, PEM file for PostgreSQL, PKCS #12 archive without
+    /// password for MySQL.
+    pub cert_file: Option<PathBuf>,
+    /// PEM file of client private key, only needed by PostgreSQL.
+    pub key_file: Option<PathBuf>,
+}
+
+/// Whether to use TLS, and how the server is verified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TlsMode {
+    /// Plain connections.
+    #[default]
+    Disable,
+    /// Encrypted connections, the server is not verified.
+    Require,
+    /// The server certificate is verified, but not its host name.
+    VerifyCa,
+    /// Both the server certificate and its host name are verified.
+    VerifyFull,
+}
+
+impl TlsMode {
+    /// Names accepted by [`TlsMode::from_str`], the same as `sslmode` of
+    /// PostgreSQL.
+    pub const NAMES: [&'static str; 4] = ["disable", "require", "verify-ca", "verify-full"];
+}
+
+impl FromStr for TlsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "disable" => Ok(TlsMode::Disable),
+            "require" => Ok(TlsMode::Require),
+            "verify-ca" => Ok(TlsMode::VerifyCa),
+            "verify-full" => Ok(TlsMode::VerifyFull),
+            other => Err(format!(
+                "Unknown TLS mode `{other}`, expect one of {}",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_env_config() {
+        let dir = std::env::temp_dir().join(format!("sqlness-env-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        std::fs::write(&path, "[sqlness]\nformat = \"jsonl\"\n[other]\nkey = 1\n").unwrap();
+        let config = EnvConfig::from_file(&path).unwrap();
+        assert_eq!(config.format, OutputFormat::JsonLines);
+
+        std::fs::write(&path, "[sqlness.splitters]\nsql = \"promql\"\n").unwrap();
+        let config = EnvConfig::from_file(&path).unwrap();
+        assert_eq!(
+            config.splitters,
+            HashMap::from([("sql".to_string(), "promql".to_string())])
+        );
+        std::fs::write(&path, "[sqlness.splitters]\nsql = 1\n").unwrap();
+        assert!(EnvConfig::from_file(&path).is_err());
+
+        std::fs::write(&path, "[other]\nkey = 1\n").unwrap();
+        let config = EnvConfig::from_file(&path).unwrap();
+        assert_eq!(config.format, OutputFormat::Table);
+
+        std::fs::write(&path, "[sqlness]\nformat = \"xml\"\n").unwrap();
+        assert!(EnvConfig::from_file(&path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn update_database_config() {
+        let dir = std::env::temp_dir().join(format!("sqlness-db-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let mut config = DatabaseConfigBuilder::default()
+            .ip_or_host("127.0.0.1".to_string())
+            .tcp_port(3306)
+            .user(Some("root".to_string()))
+            .pass(None)
+            .db_name(None)
+            .build()
+            .unwrap();
+
+        std::fs::write(
+            &path,
+            r#"
+[sqlness]
+format = "csv"
+
+[database]
+tcp_port = 3307
+db_name = "public"
+socket = "/tmp/mysql.sock"
+connect_timeout = "1s500ms"
+init_statements = ["SET a = 1", "SET b = 2"]
+params = { compress = true, application_name = "sqlness" }
+tls = { mode = "verify-ca", ca_file = "ca.pem" }
+"#,
+        )
+        .unwrap();
+        config.update_from_file(&path).unwrap();
+        assert_eq!(
+            (config.ip_or_host.as_str(), config.tcp_port),
+            ("127.0.0.1", 3307)
+        );
+        assert_eq!(config.user.as_deref(), Some("root"));
+        assert_eq!(config.db_name.as_deref(), Some("public"));
+        assert_eq!(config.socket, Some(PathBuf::from("/tmp/mysql.sock")));
+        assert_eq!(config.connect_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.init_statements, vec!["SET a = 1", "SET b = 2"]);
+        assert_eq!(
+            config.params,
+            vec![
+                ("application_name".to_string(), "sqlness".to_string()),
+                ("compress".to_string(), "true".to_string())
+            ]
+        );
+        assert_eq!(
+            config.tls,
+            TlsConfig {
+                mode: TlsMode::VerifyCa,
+                ca_file: Some(PathBuf::from("ca.pem")),
+                ..Default::default()
+            }
+        );
+
+        for content in [
+            "[database]\ntcp_port = \"3306\"\n",
+            "[database]\nport = 3306\n",
+            "[database]\ntls = { mode = \"verify\" }\n",
+            "[database]\npool_size = 0\n",
+            "[database]\npool_size = -1\n",
+        ] {
+            std::fs::write(&path, content).unwrap();
+            assert!(config.update_from_file(&path).is_err(), "{content}");
+        }
+        assert_eq!(config.pool_size, 1);
+
+        let err = DatabaseConfigBuilder::default()
+            .ip_or_host("127.0.0.1".to_string())
+            .tcp_port(3306)
+            .user(None)
+            .pass(None)
+            .db_name(None)
+            .pool_size(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "`pool_size` should be a positive integer");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
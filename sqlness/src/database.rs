@@ -4,7 +4,7 @@ use std::fmt::Display;
 
 use async_trait::async_trait;
 
-use crate::case::QueryContext;
+use crate::{case::QueryContext, result_set::ResultSet};
 
 /// Query executor.
 ///
@@ -12,9 +12,64 @@ use crate::case::QueryContext;
 /// execute query. The context parameter is a key-value pair map that
 /// usually comes from interceptor or config file.
 ///
+/// Databases are shared by concurrently running cases, hence the `Sync` bound.
+/// Queries must not block the executor, otherwise other cases are stalled
+/// and [`Config::query_timeout`] can't abort them.
+///
 /// [`Runner`]: crate::Runner
 /// [`EnvController::start`]: crate::EnvController#tymethod.start
+/// [`Config::query_timeout`]: crate::Config::query_timeout
 #[async_trait]
-pub trait Database {
+pub trait Database: Sync {
     async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display>;
+
+    /// Like [`Database::query`], but tells whether the query succeeds or fails.
+    ///
+    /// [`Runner`] always calls this method. The default implementation treats
+    /// every response of [`Database::query`] as success, implement it to make
+    /// interceptors like `EXPECT_ERROR` work.
+    ///
+    /// [`Runner`]: crate::Runner
+    async fn query_result(&self, context: QueryContext, query: String) -> QueryResult {
+        QueryResult::Output(self.query(context, query).await)
+    }
+}
+
+/// Response of one query.
+///
+/// Prefer [`QueryResult::ResultSet`] and [`QueryResult::AffectedRows`] over
+/// [`QueryResult::Output`], so sqlness renders results in the same layout
+/// regardless of the database.
+pub enum QueryResult {
+    /// The query succeeds with this output, rendered as is.
+    Output(Box<dyn Display>),
+    /// The query succeeds and returns rows.
+    ResultSet(ResultSet),
+    /// The query succeeds and modifies this number of rows.
+    AffectedRows(u64),
+    /// The query fails with this error.
+    Error(Box<dyn Display>),
+}
+
+impl QueryResult {
+    pub fn is_error(&self) -> bool {
+        matches!(self, QueryResult::Error(_))
+    }
+}
+
+impl Display for QueryResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryResult::Output(output) => output.fmt(f),
+            QueryResult::ResultSet(result_set) => result_set.fmt(f),
+            QueryResult::AffectedRows(rows) => write!(f, "affected_rows: {rows}"),
+            QueryResult::Error(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<ResultSet> for QueryResult {
+    fn from(result_set: ResultSet) -> Self {
+        QueryResult::ResultSet(result_set)
+    }
 }
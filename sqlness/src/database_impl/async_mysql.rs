@@ -0,0 +1,144 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Async MySQL backend based on `mysql_async`.
+
+use std::{fmt::Display, io};
+
+use async_trait::async_trait;
+use mysql_async::{
+    prelude::Queryable, ClientIdentity, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, Row,
+    SslOpts,
+};
+
+use super::mysql_value::to_result_set;
+use crate::{Database, DatabaseConfig, QueryContext, QueryResult, TlsConfig, TlsMode};
+
+/// Async `MysqlDatabase` with a pool of connections, see
+/// [`DatabaseConfig::pool_size`].
+///
+/// It must be used inside a tokio runtime.
+pub struct AsyncMysqlDatabase {
+    pool: Pool,
+}
+
+impl AsyncMysqlDatabase {
+    /// Create the pool and check one connection can be established.
+    pub async fn try_new(config: DatabaseConfig) -> Result<Self, mysql_async::Error> {
+        let constraints = PoolConstraints::new(0, config.pool_size)
+            .expect("min size is never larger than max size");
+        // Driver parameters are only accepted in URL.
+        let url = format!("mysql://localhost/?{}", encode_params(&config.params));
+        let opts = OptsBuilder::from_opts(Opts::from_url(&url)?)
+            .ip_or_hostname(config.ip_or_host)
+            .tcp_port(config.tcp_port)
+            .user(config.user)
+            .pass(config.pass)
+            .db_name(config.db_name)
+            .socket(config.socket.map(|path| path.display().to_string()))
+            .init(config.init_statements)
+            .ssl_opts(ssl_opts(&config.tls))
+            .pool_opts(PoolOpts::default().with_constraints(constraints));
+
+        let pool = Pool::new(opts);
+        let conn = match config.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, pool.get_conn())
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??,
+            None => pool.get_conn().await?,
+        };
+        drop(conn);
+        Ok(AsyncMysqlDatabase { pool })
+    }
+
+    pub async fn execute(&self, query: &str) -> QueryResult {
+        let mut conn = match self.pool.get_conn().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to get connection, err: {:?}",
+                    e
+                )))
+            }
+        };
+
+        let mut result = match conn.query_iter(query).await {
+            Ok(result) => result,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to execute query, err: {:?}",
+                    e
+                )))
+            }
+        };
+
+        let columns = result.columns_ref().to_vec();
+        let affected_rows = result.affected_rows();
+        let rows = match result.collect::<Row>().await {
+            Ok(rows) => rows,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to parse query result, err: {:?}",
+                    e
+                )))
+            }
+        };
+        // Consume remaining result sets, so the connection can be reused.
+        if let Err(e) = result.drop_result().await {
+            return QueryResult::Error(Box::new(format!(
+                "Failed to parse query result, err: {:?}",
+                e
+            )));
+        }
+
+        if columns.is_empty() {
+            return QueryResult::AffectedRows(affected_rows);
+        }
+        let rows = rows.into_iter().map(Row::unwrap).collect();
+        QueryResult::ResultSet(to_result_set(&columns, rows))
+    }
+}
+
+/// Like the one of `MysqlDatabase`, with types of `mysql_async`.
+fn ssl_opts(tls: &TlsConfig) -> Option<SslOpts> {
+    if tls.mode == TlsMode::Disable {
+        return None;
+    }
+
+    let opts = SslOpts::default()
+        .with_root_cert_path(tls.ca_file.clone())
+        .with_client_identity(tls.cert_file.clone().map(ClientIdentity::new))
+        .with_danger_accept_invalid_certs(tls.mode == TlsMode::Require)
+        .with_danger_skip_domain_validation(tls.mode != TlsMode::VerifyFull);
+    Some(opts)
+}
+
+/// Encode parameters as URL query string.
+fn encode_params(params: &[(String, String)]) -> String {
+    let encode = |s: &str| {
+        s.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    (byte as char).to_string()
+                }
+                byte => format!("%{byte:02X}"),
+            })
+            .collect::<String>()
+    };
+
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[async_trait]
+impl Database for AsyncMysqlDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        self.execute(&query).await
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Async PostgreSQL backend based on `tokio-postgres`.
+
+use std::fmt::Display;
+
+use async_trait::async_trait;
+use tokio_postgres::{Client, Config};
+
+pub use super::postgres_config::PostgresError;
+use super::{
+    pool::Pool,
+    postgres_config::{connection_string, make_tls},
+    postgres_value::to_query_result,
+};
+use crate::{Database, DatabaseConfig, QueryContext, QueryResult};
+
+/// Async `PostgresqlDatabase` with a pool of connections, see
+/// [`DatabaseConfig::pool_size`].
+///
+/// Connections are driven by tasks spawned on the current tokio runtime, so
+/// this must be created inside one.
+pub struct AsyncPostgresqlDatabase {
+    pool: Pool<Client>,
+}
+
+impl AsyncPostgresqlDatabase {
+    pub async fn try_new(config: &DatabaseConfig) -> Result<Self, PostgresError> {
+        let postgres_config = connection_string(config).parse::<Config>()?;
+        let tls = make_tls(&config.tls)?;
+
+        let mut clients = Vec::with_capacity(config.pool_size);
+        for _ in 0..config.pool_size {
+            let (client, connection) = postgres_config.connect(tls.clone()).await?;
+            tokio::spawn(async move {
+                if let Err(err) = connection.await {
+                    println!("PostgreSQL connection closed with error: {err:?}");
+                }
+            });
+            for statement in &config.init_statements {
+                client.batch_execute(statement).await?;
+            }
+            clients.push(client);
+        }
+
+        Ok(AsyncPostgresqlDatabase {
+            pool: Pool::new(clients),
+        })
+    }
+
+    pub async fn execute(&self, query: &str) -> QueryResult {
+        let client = self.pool.get().await;
+
+        // See `PostgresqlDatabase::execute` for why the statement is prepared.
+        let types = client.prepare(query).await.ok().map(|statement| {
+            statement
+                .columns()
+                .iter()
+                .map(|column| column.type_().clone())
+                .collect::<Vec<_>>()
+        });
+        let messages = match client.simple_query(query).await {
+            Ok(messages) => messages,
+            Err(err) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to execute query, encountered: {:?}",
+                    err
+                )))
+            }
+        };
+
+        to_query_result(types, messages)
+    }
+}
+
+#[async_trait]
+impl Database for AsyncPostgresqlDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        self.execute(&query).await
+    }
+}
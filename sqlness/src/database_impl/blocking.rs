@@ -0,0 +1,187 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Run queries of blocking clients off the executor.
+
+use std::{
+    io,
+    sync::{
+        mpsc::{self, SendError, Sender},
+        Mutex, PoisonError,
+    },
+    thread,
+};
+
+use futures::channel::oneshot;
+
+use crate::{QueryResult, ResultSet};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Thread running queries of one connection, so the returned futures yield
+/// while waiting, and queries can be abandoned by [`QueryContext::timeout`].
+///
+/// Queries run one by one in the order they are sent. An abandoned query
+/// keeps running until the client returns, so following queries wait for
+/// it, while queries abandoned before they start are skipped.
+///
+/// The thread is spawned by the first query, and exits when this is dropped.
+///
+/// [`QueryContext::timeout`]: crate::QueryContext::timeout
+#[derive(Debug, Default)]
+pub(crate) struct QueryThread {
+    jobs: Mutex<Option<Sender<Job>>>,
+}
+
+impl QueryThread {
+    pub(crate) async fn run<F>(&self, query: F) -> QueryResult
+    where
+        F: FnOnce() -> QueryResult + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            // The receiver is gone if the query is abandoned.
+            if !tx.is_canceled() {
+                let _ = tx.send(SendableResult::from(query()));
+            }
+        });
+        if let Err(e) = self.send(job) {
+            return QueryResult::Error(Box::new(format!("Failed to spawn query thread, err: {e}")));
+        }
+
+        match rx.await {
+            Ok(result) => result.into(),
+            Err(_) => QueryResult::Error(Box::new("Query thread panicked".to_string())),
+        }
+    }
+
+    /// Send `job` to the thread, which is spawned if it's not yet, or has
+    /// exited as a query panicked.
+    fn send(&self, mut job: Job) -> io::Result<()> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(sender) = jobs.as_ref() {
+            match sender.send(job) {
+                Ok(()) => return Ok(()),
+                Err(SendError(returned)) => job = returned,
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("sqlness-query".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    job();
+                }
+            })?;
+        // The thread only exits after the sender is dropped or a job panics.
+        let _ = sender.send(job);
+        *jobs = Some(sender);
+
+        Ok(())
+    }
+}
+
+/// [`QueryResult`] with outputs and errors rendered, which can be sent
+/// between threads.
+enum SendableResult {
+    Output(String),
+    ResultSet(ResultSet),
+    AffectedRows(u64),
+    Error(String),
+}
+
+impl From<QueryResult> for SendableResult {
+    fn from(result: QueryResult) -> Self {
+        match result {
+            QueryResult::Output(output) => SendableResult::Output(output.to_string()),
+            QueryResult::ResultSet(result_set) => SendableResult::ResultSet(result_set),
+            QueryResult::AffectedRows(rows) => SendableResult::AffectedRows(rows),
+            QueryResult::Error(error) => SendableResult::Error(error.to_string()),
+        }
+    }
+}
+
+impl From<SendableResult> for QueryResult {
+    fn from(result: SendableResult) -> Self {
+        match result {
+            SendableResult::Output(output) => QueryResult::Output(Box::new(output)),
+            SendableResult::ResultSet(result_set) => QueryResult::ResultSet(result_set),
+            SendableResult::AffectedRows(rows) => QueryResult::AffectedRows(rows),
+            SendableResult::Error(error) => QueryResult::Error(Box::new(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::timer;
+
+    fn thread_id() -> QueryResult {
+        QueryResult::Output(Box::new(format!("{:?}", thread::current().id())))
+    }
+
+    #[tokio::test]
+    async fn run_on_one_thread() {
+        let query_thread = QueryThread::default();
+        let result = query_thread.run(|| QueryResult::AffectedRows(1)).await;
+        assert_eq!(result.to_string(), "affected_rows: 1");
+
+        let result = query_thread
+            .run(|| QueryResult::Error(Box::new("boom".to_string())))
+            .await;
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "boom");
+
+        let first = query_thread.run(thread_id).await.to_string();
+        let second = query_thread.run(thread_id).await.to_string();
+        assert_eq!(first, second);
+        assert_ne!(first, format!("{:?}", thread::current().id()));
+    }
+
+    #[tokio::test]
+    async fn abandon_blocking_query() {
+        let query_thread = QueryThread::default();
+        let query = query_thread.run(|| {
+            thread::sleep(Duration::from_millis(200));
+            QueryResult::AffectedRows(1)
+        });
+        assert!(timer::timeout(Duration::from_millis(50), query)
+            .await
+            .is_none());
+
+        // Queries abandoned before they start are skipped.
+        let started = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&started);
+        let query = query_thread.run(move || {
+            flag.store(true, Ordering::SeqCst);
+            QueryResult::AffectedRows(2)
+        });
+        assert!(timer::timeout(Duration::from_millis(50), query)
+            .await
+            .is_none());
+
+        let result = query_thread.run(|| QueryResult::AffectedRows(3)).await;
+        assert_eq!(result.to_string(), "affected_rows: 3");
+        assert!(!started.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn respawn_after_panic() {
+        let query_thread = QueryThread::default();
+        let first = query_thread.run(thread_id).await.to_string();
+        let result = query_thread.run(|| panic!("boom")).await;
+        assert_eq!(result.to_string(), "Query thread panicked");
+
+        let second = query_thread.run(thread_id).await.to_string();
+        assert_ne!(first, second);
+    }
+}
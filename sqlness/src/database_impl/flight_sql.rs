@@ -0,0 +1,460 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Arrow Flight SQL backend based on `arrow-flight`.
+
+use std::fmt::Display;
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+        UInt32Type, UInt64Type, UInt8Type,
+    },
+    Array, RecordBatch,
+};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_flight::sql::client::FlightSqlServiceClient;
+use arrow_schema::{ArrowError, DataType, Schema};
+use async_trait::async_trait;
+use futures::{lock::Mutex, TryStreamExt};
+use tonic::transport::{Channel, Endpoint};
+
+use crate::{Column, Database, DatabaseConfig, QueryContext, QueryResult, ResultSet, Value};
+
+/// Leading keywords of statements returning rows, which are executed as
+/// Flight SQL queries. Others are executed as updates, whose result is the
+/// number of affected rows.
+const QUERY_KEYWORDS: [&str; 9] = [
+    "SELECT", "WITH", "SHOW", "DESC", "DESCRIBE", "EXPLAIN", "VALUES", "TABLE", "PRAGMA",
+];
+
+/// Statements containing this keyword return rows whatever they start with,
+/// e.g. `INSERT ... RETURNING`.
+const RETURNING_KEYWORD: &str = "RETURNING";
+
+/// Connects to `http://{ip_or_host}:{tcp_port}`, authenticates by handshake
+/// if `user` is set, and sends `db_name` in the `database` header if set.
+///
+/// Requests are sent through tonic, so this must be used inside a tokio
+/// runtime.
+///
+/// Flight SQL executes queries and updates by different calls, so statements
+/// are classified by a keyword heuristic: after skipping leading comments and
+/// parentheses, statements starting with a keyword like `SELECT`, `WITH`,
+/// `SHOW`, `EXPLAIN` or `PRAGMA`, or containing `RETURNING`, are executed as
+/// queries, and all others as updates.
+/// Statements the heuristic gets wrong can be wrapped, e.g. in
+/// `SELECT * FROM (...)`.
+pub struct FlightSqlDatabase {
+    client: Mutex<FlightSqlServiceClient<Channel>>,
+}
+
+impl FlightSqlDatabase {
+    pub async fn try_new(config: &DatabaseConfig) -> Result<Self, ArrowError> {
+        let url = format!("http://{}:{}", config.ip_or_host, config.tcp_port);
+        let mut endpoint =
+            Endpoint::from_shared(url).map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+        if let Some(timeout) = config.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        let channel = endpoint
+            .connect()
+            .await
+            .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+        let mut client = FlightSqlServiceClient::new(channel);
+
+        if let Some(user) = &config.user {
+            client
+                .handshake(user, config.pass.as_deref().unwrap_or_default())
+                .await?;
+        }
+        if let Some(db_name) = &config.db_name {
+            client.set_header("database", db_name);
+        }
+        for statement in &config.init_statements {
+            client.execute_update(statement.clone(), None).await?;
+        }
+
+        Ok(FlightSqlDatabase {
+            client: Mutex::new(client),
+        })
+    }
+
+    pub async fn execute(&self, query: &str) -> QueryResult {
+        let mut client = self.client.lock().await;
+        let result = if is_query(query) {
+            Self::fetch(&mut client, query)
+                .await
+                .map(QueryResult::ResultSet)
+        } else {
+            client
+                .execute_update(query.to_string(), None)
+                .await
+                .map(|rows| QueryResult::AffectedRows(rows.max(0) as u64))
+        };
+
+        result.unwrap_or_else(|e| {
+            QueryResult::Error(Box::new(format!("Failed to execute query, err: {e}")))
+        })
+    }
+
+    async fn fetch(
+        client: &mut FlightSqlServiceClient<Channel>,
+        query: &str,
+    ) -> Result<ResultSet, ArrowError> {
+        let info = client.execute(query.to_string(), None).await?;
+        let schema = info.clone().try_decode_schema()?;
+
+        let mut batches = Vec::new();
+        for endpoint in info.endpoint {
+            let Some(ticket) = endpoint.ticket else {
+                continue;
+            };
+            let stream = client.do_get(ticket).await?;
+            let stream_batches = stream
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+            batches.extend(stream_batches);
+        }
+
+        to_result_set(&schema, &batches)
+    }
+}
+
+fn is_query(query: &str) -> bool {
+    let query = skip_leading_comments(query);
+    let mut words = query.split(|c: char| !c.is_ascii_alphanumeric() && c != '_');
+    let keyword = words.next().unwrap_or_default();
+    QUERY_KEYWORDS
+        .iter()
+        .any(|query_keyword| keyword.eq_ignore_ascii_case(query_keyword))
+        || words.any(|word| word.eq_ignore_ascii_case(RETURNING_KEYWORD))
+}
+
+/// Skip whitespaces, opening parentheses, `--` and `/* */` comments at the
+/// start of `query`.
+fn skip_leading_comments(mut query: &str) -> &str {
+    loop {
+        query = query.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+        if let Some(rest) = query.strip_prefix("--") {
+            query = rest.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(rest) = query.strip_prefix("/*") {
+            query = rest.split_once("*/").map_or("", |(_, rest)| rest);
+        } else {
+            return query;
+        }
+    }
+}
+
+/// Convert record batches to result set, columns come from `schema` in case
+/// there is no batch.
+fn to_result_set(schema: &Schema, batches: &[RecordBatch]) -> Result<ResultSet, ArrowError> {
+    let mut result_set = ResultSet::new(
+        schema
+            .fields()
+            .iter()
+            .map(|field| Column::new(field.name(), field.data_type().to_string()))
+            .collect(),
+    );
+
+    let options = FormatOptions::default().with_null("NULL");
+    for batch in batches {
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|array| ArrayFormatter::try_new(array, &options))
+            .collect::<Result<Vec<_>, _>>()?;
+        for row in 0..batch.num_rows() {
+            let values = batch
+                .columns()
+                .iter()
+                .zip(&formatters)
+                .map(|(array, formatter)| convert_value(array, formatter, row))
+                .collect();
+            result_set.push_row(values);
+        }
+    }
+
+    Ok(result_set)
+}
+
+fn convert_value(array: &dyn Array, formatter: &ArrayFormatter, row: usize) -> Value {
+    if array.is_null(row) {
+        return Value::Null;
+    }
+
+    match array.data_type() {
+        DataType::Null => Value::Null,
+        DataType::Boolean => Value::Bool(array.as_boolean().value(row)),
+        DataType::Int8 => Value::Int(array.as_primitive::<Int8Type>().value(row).into()),
+        DataType::Int16 => Value::Int(array.as_primitive::<Int16Type>().value(row).into()),
+        DataType::Int32 => Value::Int(array.as_primitive::<Int32Type>().value(row).into()),
+        DataType::Int64 => Value::Int(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt8 => Value::UInt(array.as_primitive::<UInt8Type>().value(row).into()),
+        DataType::UInt16 => Value::UInt(array.as_primitive::<UInt16Type>().value(row).into()),
+        DataType::UInt32 => Value::UInt(array.as_primitive::<UInt32Type>().value(row).into()),
+        DataType::UInt64 => Value::UInt(array.as_primitive::<UInt64Type>().value(row)),
+        DataType::Float32 => Value::Float(array.as_primitive::<Float32Type>().value(row).into()),
+        DataType::Float64 => Value::Float(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => Value::String(array.as_string::<i32>().value(row).to_string()),
+        DataType::LargeUtf8 => Value::String(array.as_string::<i64>().value(row).to_string()),
+        DataType::Binary => Value::Bytes(array.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => Value::Bytes(array.as_binary::<i64>().value(row).to_vec()),
+        DataType::FixedSizeBinary(_) => {
+            Value::Bytes(array.as_fixed_size_binary().value(row).to_vec())
+        }
+        DataType::Decimal128(..) | DataType::Decimal256(..) => {
+            Value::Decimal(formatter.value(row).to_string())
+        }
+        DataType::Date32 | DataType::Date64 => Value::Date(formatter.value(row).to_string()),
+        DataType::Time32(_) | DataType::Time64(_) => Value::Time(formatter.value(row).to_string()),
+        DataType::Timestamp(..) => Value::Timestamp(formatter.value(row).to_string()),
+        // Durations, intervals, lists, structs and so on.
+        _ => Value::String(formatter.value(row).to_string()),
+    }
+}
+
+#[async_trait]
+impl Database for FlightSqlDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        self.execute(&query).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{pin::Pin, sync::Arc};
+
+    use arrow_array::{
+        BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int32Array,
+        ListArray, StringArray, TimestampMillisecondArray, UInt64Array,
+    };
+    use arrow_flight::{
+        encode::FlightDataEncoderBuilder,
+        flight_service_server::{FlightService, FlightServiceServer},
+        sql::{
+            server::{FlightSqlService, PeekableFlightDataStream},
+            CommandStatementQuery, CommandStatementUpdate, ProstMessageExt, SqlInfo,
+            TicketStatementQuery,
+        },
+        FlightDescriptor, FlightEndpoint, FlightInfo, Ticket,
+    };
+    use arrow_schema::{Field, TimeUnit};
+    use futures::stream;
+    use prost::Message;
+    use tonic::{
+        transport::{server::TcpIncoming, Server},
+        Request, Response, Status,
+    };
+
+    use super::*;
+    use crate::DatabaseConfigBuilder;
+
+    /// Flight SQL server answering `SELECT ...` with one row of the query
+    /// text, updates with the length of the query, and failing queries
+    /// containing `error`.
+    struct StubService;
+
+    impl StubService {
+        fn schema() -> Schema {
+            Schema::new(vec![Field::new("query", DataType::Utf8, false)])
+        }
+    }
+
+    #[tonic::async_trait]
+    impl FlightSqlService for StubService {
+        type FlightService = StubService;
+
+        async fn get_flight_info_statement(
+            &self,
+            query: CommandStatementQuery,
+            _: Request<FlightDescriptor>,
+        ) -> Result<Response<FlightInfo>, Status> {
+            if query.query.contains("error") {
+                return Err(Status::invalid_argument("bad query"));
+            }
+            let ticket = TicketStatementQuery {
+                statement_handle: query.query.into(),
+            };
+            let info = FlightInfo::new()
+                .try_with_schema(&Self::schema())
+                .map_err(|e| Status::internal(e.to_string()))?
+                .with_endpoint(
+                    FlightEndpoint::new().with_ticket(Ticket::new(ticket.as_any().encode_to_vec())),
+                );
+            Ok(Response::new(info))
+        }
+
+        async fn do_get_statement(
+            &self,
+            ticket: TicketStatementQuery,
+            _: Request<Ticket>,
+        ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+            let query = String::from_utf8_lossy(&ticket.statement_handle).into_owned();
+            let batch = RecordBatch::try_new(
+                Arc::new(Self::schema()),
+                vec![Arc::new(StringArray::from(vec![query]))],
+            )
+            .map_err(|e| Status::internal(e.to_string()))?;
+            let stream = FlightDataEncoderBuilder::new()
+                .with_schema(Arc::new(Self::schema()))
+                .build(stream::iter([Ok(batch)]))
+                .map_err(Status::from);
+            Ok(Response::new(Box::pin(stream) as Pin<Box<_>>))
+        }
+
+        async fn do_put_statement_update(
+            &self,
+            command: CommandStatementUpdate,
+            _: Request<PeekableFlightDataStream>,
+        ) -> Result<i64, Status> {
+            if command.query.contains("error") {
+                return Err(Status::invalid_argument("bad update"));
+            }
+            Ok(command.query.len() as i64)
+        }
+
+        async fn register_sql_info(&self, _: i32, _: &SqlInfo) {}
+    }
+
+    #[tokio::test]
+    async fn execute_against_stub_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(StubService))
+                .serve_with_incoming(incoming),
+        );
+
+        let config = DatabaseConfigBuilder::default()
+            .ip_or_host("127.0.0.1".to_string())
+            .tcp_port(port)
+            .user(None)
+            .pass(None)
+            .db_name(None)
+            .build()
+            .unwrap();
+        let db = FlightSqlDatabase::try_new(&config).await.unwrap();
+
+        let result = db.execute("SELECT 1;").await;
+        assert!(!result.is_error(), "{result}");
+        let QueryResult::ResultSet(result_set) = result else {
+            panic!("expect result set, got {result}");
+        };
+        assert_eq!(result_set.columns, vec![Column::new("query", "Utf8")]);
+        assert_eq!(result_set.rows, vec![vec![Value::from("SELECT 1;")]]);
+
+        let result = db.execute("DELETE FROM t;").await;
+        assert_eq!(result.to_string(), "affected_rows: 14");
+
+        for query in ["SELECT error;", "DELETE error;"] {
+            let result = db.execute(query).await;
+            assert!(result.is_error(), "{result}");
+            assert!(result.to_string().contains("bad "), "{result}");
+        }
+    }
+
+    #[test]
+    fn classify_statements() {
+        for query in [
+            "SELECT 1;",
+            "  with t AS (SELECT 1) SELECT * FROM t;",
+            "(SELECT 1);",
+            "/* hint */ SELECT 1;",
+            "-- note\n  /* a */ /* b */\nSELECT 1;",
+            "INSERT INTO t VALUES (1) RETURNING id;",
+            "PRAGMA table_info(t);",
+        ] {
+            assert!(is_query(query), "{query}");
+        }
+        for query in [
+            "INSERT INTO t VALUES (1);",
+            "CREATE TABLE t(a INT);",
+            "SELECTX;",
+            "INSERT INTO t(returning_id) VALUES (1);",
+            "/* SELECT */ DELETE FROM t;",
+            "-- SELECT",
+            "",
+        ] {
+            assert!(!is_query(query), "{query}");
+        }
+    }
+
+    #[test]
+    fn convert_record_batches() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("b", DataType::Boolean, true),
+            Field::new("i", DataType::Int32, true),
+            Field::new("u", DataType::UInt64, true),
+            Field::new("f", DataType::Float64, true),
+            Field::new("s", DataType::Utf8, true),
+            Field::new("bin", DataType::Binary, true),
+            Field::new("d", DataType::Decimal128(5, 2), true),
+            Field::new("date", DataType::Date32, true),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+            Field::new(
+                "l",
+                DataType::List(Arc::new(Field::new("item", DataType::Int32, true))),
+                true,
+            ),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(BooleanArray::from(vec![Some(true), None])),
+                Arc::new(Int32Array::from(vec![Some(-1), None])),
+                Arc::new(UInt64Array::from(vec![Some(u64::MAX), None])),
+                Arc::new(Float64Array::from(vec![Some(1.5), None])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+                Arc::new(BinaryArray::from(vec![Some(b"\x0a\xff".as_ref()), None])),
+                Arc::new(
+                    Decimal128Array::from(vec![Some(12345), None])
+                        .with_precision_and_scale(5, 2)
+                        .unwrap(),
+                ),
+                Arc::new(Date32Array::from(vec![Some(19723), None])),
+                Arc::new(TimestampMillisecondArray::from(vec![
+                    Some(1_704_067_200_123),
+                    None,
+                ])),
+                Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                    Some(vec![Some(1), None]),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let result_set = to_result_set(&schema, &[batch]).unwrap();
+        assert_eq!(result_set.columns[6], Column::new("d", "Decimal128(5, 2)"));
+        assert_eq!(
+            result_set.rows,
+            vec![
+                vec![
+                    Value::Bool(true),
+                    Value::Int(-1),
+                    Value::UInt(u64::MAX),
+                    Value::Float(1.5),
+                    Value::from("a"),
+                    Value::Bytes(vec![0x0a, 0xff]),
+                    Value::Decimal("123.45".to_string()),
+                    Value::Date("2024-01-01".to_string()),
+                    Value::Timestamp("2024-01-01T00:00:00.123".to_string()),
+                    Value::from("[1, NULL]"),
+                ],
+                vec![Value::Null; 10],
+            ]
+        );
+
+        let result_set = to_result_set(&schema, &[]).unwrap();
+        assert_eq!(result_set.columns.len(), 10);
+        assert!(result_set.rows.is_empty());
+    }
+}
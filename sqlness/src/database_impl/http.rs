@@ -0,0 +1,844 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Generic backend for databases that accept queries over HTTP and respond
+//! in JSON, like ClickHouse, Databend and GreptimeDB.
+//!
+//! Each query is sent as one HTTP/1.1 request, whose URL and body are
+//! [minijinja](https://docs.rs/minijinja) templates rendered with:
+//! - `query`: the query to execute.
+//! - `context`: the key-value map of [`QueryContext`], e.g. set by `ARG`
+//!   interceptor.
+//!
+//! Besides builtin filters, `urlencode` and `tojson` are available to escape
+//! the query in URL and JSON body respectively.
+//!
+//! The JSON response is mapped to [`QueryResult`] by paths in
+//! [`ResponseMapping`], see its document for details.
+//!
+//! Only plain `http://` URLs are supported.
+//!
+//! Requests are bounded by [`QueryContext::timeout`] if set, or
+//! [`HttpConfig::timeout`] otherwise.
+
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use derive_builder::Builder;
+use minijinja::{context, Environment};
+use serde_json::Value as Json;
+use thiserror::Error;
+
+use super::blocking::QueryThread;
+use crate::{Column, Database, QueryContext, QueryResult, ResultSet, Value};
+
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("Invalid url {url}, msg:{msg}")]
+    InvalidUrl { url: String, msg: String },
+
+    #[error("Invalid JSON path {path}, msg:{msg}")]
+    InvalidPath { path: String, msg: String },
+
+    #[error("Failed to render template, err:{0}")]
+    Template(#[from] minijinja::Error),
+
+    #[error("Failed to send request, err:{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Invalid response, msg:{0}")]
+    InvalidResponse(String),
+}
+
+/// Config of [`HttpDatabase`].
+#[derive(Debug, Builder, Clone)]
+pub struct HttpConfig {
+    /// URL template, e.g. `http://127.0.0.1:8123/?query={{ query | urlencode }}`
+    pub url: String,
+    /// Default value: `POST`
+    #[builder(default = "HttpConfig::default_method()")]
+    pub method: String,
+    /// Extra request headers, like `Authorization` and `Content-Type`.
+    /// Default value: none
+    #[builder(default = "HttpConfig::default_headers()")]
+    pub headers: Vec<(String, String)>,
+    /// Body template, e.g. `{"sql": {{ query | tojson }}}`. No body is sent
+    /// if empty.
+    /// Default value: `{{ query }}`
+    #[builder(default = "HttpConfig::default_body()")]
+    pub body: String,
+    /// How to map the JSON response to query result.
+    /// Default value: no mapping, the response is kept as is.
+    #[builder(default = "HttpConfig::default_mapping()")]
+    pub mapping: ResponseMapping,
+    /// Max time to connect, send the request and receive the response, when
+    /// the query has no timeout.
+    /// Default value: 30s
+    #[builder(default = "HttpConfig::default_timeout()")]
+    pub timeout: Duration,
+}
+
+impl HttpConfig {
+    fn default_method() -> String {
+        "POST".to_string()
+    }
+
+    fn default_headers() -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn default_body() -> String {
+        "{{ query }}".to_string()
+    }
+
+    fn default_mapping() -> ResponseMapping {
+        ResponseMapping::default()
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(30)
+    }
+}
+
+/// JSON paths to locate parts of query result in the response.
+///
+/// Paths are a subset of JSONPath: `$` for the root, `.key` or `['key']` for
+/// an object member, `[0]` for an array element, and `.*` or `[*]` for all
+/// members or elements.
+///
+/// - `error`: error message, the query fails if it exists and is not null.
+/// - `rows`: an array of rows. A row is either an array of values, or an
+///   object keyed by column names.
+/// - `columns`: column names, either an array, or all matches of a wildcard
+///   path like `$.meta[*].name`. When missing, columns are the keys of the
+///   first row if it's an object, or `column1`, `column2`... otherwise.
+/// - `types`: column types, located the same way as `columns`.
+/// - `affected_rows`: number of affected rows, used when `rows` is missing.
+///
+/// For example, mapping of ClickHouse `JSONCompact` format is:
+/// ``` text
+/// columns:       $.meta[*].name
+/// types:         $.meta[*].type
+/// rows:          $.data
+/// ```
+///
+/// If none of them is found, the response is kept as is. A response with
+/// status other than 2xx is an error.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMapping {
+    pub error: Option<JsonPath>,
+    pub rows: Option<JsonPath>,
+    pub columns: Option<JsonPath>,
+    pub types: Option<JsonPath>,
+    pub affected_rows: Option<JsonPath>,
+}
+
+impl ResponseMapping {
+    /// Set the path by its name, e.g. `rows`.
+    pub fn set(&mut self, name: &str, path: &str) -> Result<(), HttpError> {
+        let path = Some(path.parse()?);
+        match name {
+            "error" => self.error = path,
+            "rows" => self.rows = path,
+            "columns" => self.columns = path,
+            "types" => self.types = path,
+            "affected_rows" => self.affected_rows = path,
+            _ => {
+                return Err(HttpError::InvalidPath {
+                    path: name.to_string(),
+                    msg: "expect one of error, rows, columns, types, affected_rows".to_string(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.error.is_none()
+            && self.rows.is_none()
+            && self.columns.is_none()
+            && self.types.is_none()
+            && self.affected_rows.is_none()
+    }
+
+    fn map(&self, status: u16, body: String) -> QueryResult {
+        let is_success = (200..300).contains(&status);
+        let json = if self.is_empty() {
+            None
+        } else {
+            serde_json::from_str::<Json>(&body).ok()
+        };
+        let Some(json) = json else {
+            let body = body.trim_end().to_string();
+            return if is_success {
+                QueryResult::Output(Box::new(body))
+            } else {
+                QueryResult::Error(Box::new(format!("HTTP {status}: {body}")))
+            };
+        };
+
+        if let Some(error) = first_match(&self.error, &json).filter(|error| !error.is_null()) {
+            return QueryResult::Error(Box::new(json_to_string(error)));
+        }
+        if !is_success {
+            return QueryResult::Error(Box::new(format!("HTTP {status}: {}", body.trim_end())));
+        }
+
+        if let Some(rows) = first_match(&self.rows, &json) {
+            let Some(rows) = rows.as_array() else {
+                return QueryResult::Error(Box::new(format!(
+                    "Invalid response, rows is not an array: {rows}"
+                )));
+            };
+            return QueryResult::ResultSet(self.to_result_set(&json, rows));
+        }
+        if let Some(affected_rows) = first_match(&self.affected_rows, &json) {
+            return match affected_rows.as_u64() {
+                Some(affected_rows) => QueryResult::AffectedRows(affected_rows),
+                None => QueryResult::Error(Box::new(format!(
+                    "Invalid response, affected rows is not a number: {affected_rows}"
+                ))),
+            };
+        }
+
+        QueryResult::Output(Box::new(body.trim_end().to_string()))
+    }
+
+    fn to_result_set(&self, json: &Json, rows: &[Json]) -> ResultSet {
+        let mut names = all_matches(&self.columns, json)
+            .into_iter()
+            .map(json_to_string)
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            names = match rows.first() {
+                Some(Json::Object(row)) => row.keys().cloned().collect(),
+                Some(Json::Array(row)) => (1..=row.len()).map(|i| format!("column{i}")).collect(),
+                _ => Vec::new(),
+            };
+        }
+        let types = all_matches(&self.types, json)
+            .into_iter()
+            .map(json_to_string)
+            .collect::<Vec<_>>();
+
+        let columns = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Column::new(name, types.get(i).cloned().unwrap_or_default()))
+            .collect();
+        let mut result_set = ResultSet::new(columns);
+        for row in rows {
+            let values = match row {
+                Json::Array(row) => (0..names.len())
+                    .map(|i| row.get(i).map_or(Value::Null, json_to_value))
+                    .collect(),
+                Json::Object(row) => names
+                    .iter()
+                    .map(|name| row.get(name).map_or(Value::Null, json_to_value))
+                    .collect(),
+                // A scalar row has only one value.
+                value => (0..names.len())
+                    .map(|i| {
+                        if i == 0 {
+                            json_to_value(value)
+                        } else {
+                            Value::Null
+                        }
+                    })
+                    .collect(),
+            };
+            result_set.push_row(values);
+        }
+
+        result_set
+    }
+}
+
+fn first_match<'a>(path: &Option<JsonPath>, json: &'a Json) -> Option<&'a Json> {
+    path.as_ref()
+        .and_then(|path| path.select(json).into_iter().next())
+}
+
+/// All matches of the path, a single array match is flattened.
+fn all_matches<'a>(path: &Option<JsonPath>, json: &'a Json) -> Vec<&'a Json> {
+    let Some(path) = path else {
+        return Vec::new();
+    };
+    match path.select(json).as_slice() {
+        [Json::Array(values)] => values.iter().collect(),
+        matches => matches.to_vec(),
+    }
+}
+
+/// Strings are rendered without quotes, other values as JSON.
+fn json_to_string(json: &Json) -> String {
+    match json {
+        Json::String(v) => v.clone(),
+        json => json.to_string(),
+    }
+}
+
+fn json_to_value(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(v) => Value::Bool(*v),
+        Json::Number(v) => {
+            if let Some(v) = v.as_i64() {
+                Value::Int(v)
+            } else if let Some(v) = v.as_u64() {
+                Value::UInt(v)
+            } else {
+                Value::Float(v.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        Json::String(v) => Value::String(v.clone()),
+        json => Value::String(json.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// A path to locate values in JSON, see [`ResponseMapping`] for its syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Select all values matching this path.
+    pub fn select<'a>(&self, json: &'a Json) -> Vec<&'a Json> {
+        let mut current = vec![json];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&Json> {
+                    match (segment, value) {
+                        (Segment::Key(key), Json::Object(map)) => {
+                            map.get(key).into_iter().collect()
+                        }
+                        (Segment::Index(index), Json::Array(array)) => {
+                            array.get(*index).into_iter().collect()
+                        }
+                        (Segment::Wildcard, Json::Array(array)) => array.iter().collect(),
+                        (Segment::Wildcard, Json::Object(map)) => map.values().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+
+        current
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = HttpError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| HttpError::InvalidPath {
+            path: path.to_string(),
+            msg: msg.to_string(),
+        };
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("should start with `$`"))?;
+
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let key = &after_dot[..end];
+                segments.push(match key {
+                    "" => return Err(invalid("empty key")),
+                    "*" => Segment::Wildcard,
+                    key => Segment::Key(key.to_string()),
+                });
+                rest = &after_dot[end..];
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let end = after_bracket
+                    .find(']')
+                    .ok_or_else(|| invalid("unclosed `[`"))?;
+                let inner = after_bracket[..end].trim();
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|inner| inner.strip_suffix('\''))
+                    .or_else(|| {
+                        inner
+                            .strip_prefix('"')
+                            .and_then(|inner| inner.strip_suffix('"'))
+                    });
+                segments.push(match (inner, quoted) {
+                    (_, Some(key)) => Segment::Key(key.to_string()),
+                    ("*", _) => Segment::Wildcard,
+                    (index, _) => Segment::Index(
+                        index
+                            .parse()
+                            .map_err(|_| invalid("expect index, `*` or quoted key in `[]`"))?,
+                    ),
+                });
+                rest = &after_bracket[end + 1..];
+            } else {
+                return Err(invalid("expect `.` or `[`"));
+            }
+        }
+
+        Ok(JsonPath { segments })
+    }
+}
+
+/// Database accessed over HTTP, see [module level document](self).
+#[derive(Clone)]
+pub struct HttpDatabase {
+    config: Arc<HttpConfig>,
+    env: Arc<Environment<'static>>,
+    /// Requests are sent one by one, like queries over one connection.
+    thread: Arc<QueryThread>,
+}
+
+impl HttpDatabase {
+    pub fn try_new(config: HttpConfig) -> Result<Self, HttpError> {
+        let mut env = Environment::new();
+        env.add_filter("urlencode", urlencode);
+        env.add_filter("tojson", tojson);
+        // Check templates are valid at once.
+        env.template_from_str(&config.url)?;
+        env.template_from_str(&config.body)?;
+
+        Ok(HttpDatabase {
+            config: Arc::new(config),
+            env: Arc::new(env),
+            thread: Arc::default(),
+        })
+    }
+
+    pub fn execute(&self, context: &QueryContext, query: &str) -> QueryResult {
+        match self.send(context, query) {
+            Ok((status, body)) => self.config.mapping.map(status, body),
+            Err(e) => QueryResult::Error(Box::new(e.to_string())),
+        }
+    }
+
+    /// Send the query, return status and body of the response.
+    fn send(&self, context: &QueryContext, query: &str) -> Result<(u16, String), HttpError> {
+        let ctx = context! { query => query, context => &context.context };
+        let url = self.env.render_str(&self.config.url, &ctx)?;
+        let body = self.env.render_str(&self.config.body, &ctx)?;
+        let url = Url::parse(&url)?;
+
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.config.method,
+            url.path,
+            url.authority,
+            body.len()
+        );
+        for (name, value) in &self.config.headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let timeout = context.timeout.unwrap_or(self.config.timeout);
+        let deadline = Instant::now() + timeout;
+        let timed_out = |e: io::Error| match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => HttpError::Timeout(timeout),
+            _ => HttpError::Io(e),
+        };
+
+        let mut stream = connect(&url, timeout).map_err(timed_out)?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.write_all(request.as_bytes()).map_err(timed_out)?;
+        let mut response = Vec::new();
+        let mut buf = [0; 8192];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(HttpError::Timeout(timeout));
+            }
+            stream.set_read_timeout(Some(remaining))?;
+            match stream.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => response.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(timed_out(e)),
+            }
+        }
+
+        parse_response(&response)
+    }
+}
+
+/// Connect to the first reachable address of `url` in `timeout`.
+fn connect(url: &Url, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in (url.host.as_str(), url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No address resolved for {}", url.host),
+        )
+    }))
+}
+
+#[async_trait]
+impl Database for HttpDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, context: QueryContext, query: String) -> QueryResult {
+        let db = self.clone();
+        self.thread.run(move || db.execute(&context, &query)).await
+    }
+}
+
+fn urlencode(value: String) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+fn tojson(value: String) -> String {
+    Json::String(value).to_string()
+}
+
+struct Url {
+    /// `host:port` part of the URL.
+    authority: String,
+    host: String,
+    port: u16,
+    /// Path and query string, e.g. `/query?database=public`.
+    path: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Self, HttpError> {
+        let invalid = |msg: &str| HttpError::InvalidUrl {
+            url: url.to_string(),
+            msg: msg.to_string(),
+        };
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http:// is supported"))?;
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !authority.ends_with(']') => {
+                (host, port.parse().map_err(|_| invalid("invalid port"))?)
+            }
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid("empty host"));
+        }
+
+        Ok(Url {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+}
+
+fn parse_response(response: &[u8]) -> Result<(u16, String), HttpError> {
+    let invalid = |msg: &str| HttpError::InvalidResponse(msg.to_string());
+    let head_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete headers"))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let mut body = &response[head_end + 4..];
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("invalid status line"))?;
+    let mut chunked = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            let length = value
+                .parse()
+                .map_err(|_| invalid("invalid content length"))?;
+            body = body.get(..length).unwrap_or(body);
+        }
+    }
+
+    let body = if chunked {
+        decode_chunked(body).ok_or_else(|| invalid("invalid chunked body"))?
+    } else {
+        body.to_vec()
+    };
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        // Ignore chunk extensions after `;`.
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::BufRead, io::BufReader, net::TcpListener, thread::JoinHandle};
+
+    use super::*;
+
+    /// Serve one request with given response, return the server address and
+    /// a handle to get the request it receives.
+    fn serve_once(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.strip_prefix("Content-Length: ") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        (addr, handle)
+    }
+
+    fn mapping(paths: &[(&str, &str)]) -> ResponseMapping {
+        let mut mapping = ResponseMapping::default();
+        for (name, path) in paths {
+            mapping.set(name, path).unwrap();
+        }
+        mapping
+    }
+
+    #[test]
+    fn parse_json_path() {
+        let json: Json = serde_json::from_str(
+            r#"{"meta": [{"name": "a"}, {"name": "b"}], "data": [[1, "x"]], "k.v": 1}"#,
+        )
+        .unwrap();
+        let select = |path: &str| path.parse::<JsonPath>().unwrap().select(&json);
+
+        assert_eq!(select("$.meta[*].name"), vec!["a", "b"]);
+        assert_eq!(select("$.meta[1]['name']"), vec!["b"]);
+        assert_eq!(select("$.data[0][1]"), vec!["x"]);
+        assert_eq!(select("$['k.v']"), vec![1]);
+        assert_eq!(select("$.missing"), Vec::<&Json>::new());
+        assert_eq!(select("$").len(), 1);
+
+        for path in ["meta", "$.", "$[", "$[x]", "$meta"] {
+            assert!(path.parse::<JsonPath>().is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn parse_url() {
+        let url = Url::parse("http://localhost:8123?query=1").unwrap();
+        assert_eq!(
+            (url.host.as_str(), url.port, url.path.as_str()),
+            ("localhost", 8123, "/?query=1")
+        );
+        let url = Url::parse("http://[::1]/v1/sql").unwrap();
+        assert_eq!(
+            (url.host.as_str(), url.port, url.path.as_str()),
+            ("::1", 80, "/v1/sql")
+        );
+        assert!(Url::parse("https://localhost").is_err());
+        assert!(Url::parse("http://localhost:port").is_err());
+    }
+
+    #[test]
+    fn map_response() {
+        let mapping = mapping(&[
+            ("columns", "$.meta[*].name"),
+            ("types", "$.meta[*].type"),
+            ("rows", "$.data"),
+            ("error", "$.error"),
+            ("affected_rows", "$.affected"),
+        ]);
+
+        let body = r#"{"meta": [{"name": "a", "type": "Int32"}, {"name": "b", "type": "String"}],
+            "data": [[1, "x"], [null, "y"]]}"#;
+        let QueryResult::ResultSet(result_set) = mapping.map(200, body.to_string()) else {
+            panic!("expect result set");
+        };
+        assert_eq!(
+            result_set.columns,
+            vec![Column::new("a", "Int32"), Column::new("b", "String")]
+        );
+        assert_eq!(
+            result_set.rows,
+            vec![
+                vec![Value::Int(1), Value::from("x")],
+                vec![Value::Null, Value::from("y")]
+            ]
+        );
+
+        // Columns of object rows are in the order of the response.
+        let result = mapping.map(200, r#"{"data": [{"b": 1, "a": 2.5}]}"#.to_string());
+        assert_eq!(
+            result.to_string(),
+            "+---+-----+\n| b | a   |\n+---+-----+\n| 1 | 2.5 |\n+---+-----+"
+        );
+
+        let result = mapping.map(200, r#"{"affected": 3}"#.to_string());
+        assert_eq!(result.to_string(), "affected_rows: 3");
+
+        let result = mapping.map(400, r#"{"error": "table not found"}"#.to_string());
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "table not found");
+
+        let result = mapping.map(500, "internal error\n".to_string());
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "HTTP 500: internal error");
+
+        let result = ResponseMapping::default().map(200, "1\tx\n".to_string());
+        assert!(!result.is_error());
+        assert_eq!(result.to_string(), "1\tx");
+    }
+
+    #[test]
+    fn query_stub_server() {
+        let (addr, handle) = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n\
+             f\r\n{\"rows\": [[1]],\r\n\
+             14\r\n \"columns\": [\"one\"]}\r\n\
+             0\r\n\r\n",
+        );
+        let config = HttpConfigBuilder::default()
+            .url(format!(
+                "http://{addr}/query?db={{{{ context.db }}}}&q={{{{ query | urlencode }}}}"
+            ))
+            .headers(vec![("Authorization".to_string(), "Basic abc".to_string())])
+            .body(r#"{"sql": {{ query | tojson }}}"#.to_string())
+            .mapping(mapping(&[("rows", "$.rows"), ("columns", "$.columns")]))
+            .build()
+            .unwrap();
+        let db = HttpDatabase::try_new(config).unwrap();
+        let mut context = QueryContext::default();
+        context
+            .context
+            .insert("db".to_string(), "public".to_string());
+
+        let result = db.execute(&context, "SELECT \"1\" AS one;");
+        assert_eq!(
+            result.to_string(),
+            "+-----+\n| one |\n+-----+\n| 1   |\n+-----+"
+        );
+
+        let request = handle.join().unwrap();
+        assert!(request
+            .starts_with("POST /query?db=public&q=SELECT%20%221%22%20AS%20one%3B HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nAuthorization: Basic abc\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"sql\": \"SELECT \\\"1\\\" AS one;\"}"));
+    }
+
+    #[test]
+    fn connection_error() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = HttpConfigBuilder::default()
+            .url(format!("http://{addr}/"))
+            .build()
+            .unwrap();
+        let db = HttpDatabase::try_new(config).unwrap();
+        assert!(db.execute(&QueryContext::default(), "SELECT 1;").is_error());
+    }
+
+    #[test]
+    fn request_timeout() {
+        // Connections are accepted by backlog, but never responded.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = HttpConfigBuilder::default()
+            .url(format!("http://{addr}/"))
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let db = HttpDatabase::try_new(config.clone()).unwrap();
+        let result = db.execute(&QueryContext::default(), "SELECT 1;");
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "Request timed out after 100ms");
+
+        let db = HttpDatabase::try_new(HttpConfig {
+            timeout: Duration::from_secs(30),
+            ..config
+        })
+        .unwrap();
+        let context = QueryContext {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let result = db.execute(&context, "SELECT 1;");
+        assert_eq!(result.to_string(), "Request timed out after 50ms");
+        assert!(now.elapsed() < Duration::from_secs(5));
+    }
+}
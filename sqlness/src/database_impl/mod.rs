@@ -1,6 +1,37 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
+#[cfg(feature = "mysql-async")]
+pub mod async_mysql;
+#[cfg(feature = "postgres-async")]
+pub mod async_postgresql;
+#[cfg(any(
+    feature = "http",
+    feature = "mysql",
+    feature = "postgres",
+    feature = "sqlite",
+    feature = "odbc",
+    feature = "process"
+))]
+mod blocking;
+#[cfg(feature = "flight-sql")]
+pub mod flight_sql;
+#[cfg(feature = "http")]
+pub mod http;
 #[cfg(feature = "mysql")]
 pub mod mysql;
+#[cfg(any(feature = "mysql", feature = "mysql-async"))]
+mod mysql_value;
+#[cfg(feature = "odbc")]
+pub mod odbc;
+#[cfg(feature = "postgres-async")]
+mod pool;
+#[cfg(any(feature = "postgres", feature = "postgres-async"))]
+mod postgres_config;
+#[cfg(any(feature = "postgres", feature = "postgres-async"))]
+mod postgres_value;
 #[cfg(feature = "postgres")]
 pub mod postgresql;
+#[cfg(feature = "process")]
+pub mod process;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
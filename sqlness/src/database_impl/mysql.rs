@@ -2,101 +2,111 @@
 
 /// DatabaseBuilder for MySQL.
 use std::{
-    borrow::{Borrow, Cow},
+    collections::HashMap,
     fmt::Display,
     sync::{Arc, Mutex},
 };
 
 use async_trait::async_trait;
-use mysql::{prelude::Queryable, Conn, OptsBuilder, Row};
+use mysql::{prelude::Queryable, ClientIdentity, Conn, OptsBuilder, SslOpts};
 
-use crate::{Database, DatabaseConfig, QueryContext};
+use super::{blocking::QueryThread, mysql_value::to_result_set};
+use crate::{Database, DatabaseConfig, QueryContext, QueryResult, TlsConfig, TlsMode};
 
 #[derive(Debug)]
 pub struct MysqlDatabase {
     conn: Arc<Mutex<Conn>>,
+    thread: QueryThread,
 }
 
 impl MysqlDatabase {
     pub fn try_new(config: DatabaseConfig) -> Result<MysqlDatabase, mysql::Error> {
+        let params = config.params.iter().cloned().collect::<HashMap<_, _>>();
         let opts = OptsBuilder::new()
+            .from_hash_map(&params)?
             .ip_or_hostname(Some(config.ip_or_host.clone()))
             .tcp_port(config.tcp_port)
             .user(config.user.clone())
             .pass(config.pass.clone())
-            .db_name(config.db_name);
+            .db_name(config.db_name)
+            .socket(config.socket.map(|path| path.display().to_string()))
+            .tcp_connect_timeout(config.connect_timeout)
+            .init(config.init_statements)
+            .ssl_opts(ssl_opts(&config.tls));
 
         let conn = Conn::new(opts)?;
         Ok(MysqlDatabase {
             conn: Arc::new(Mutex::new(conn)),
+            thread: QueryThread::default(),
         })
     }
 }
 
+fn ssl_opts(tls: &TlsConfig) -> Option<SslOpts> {
+    if tls.mode == TlsMode::Disable {
+        return None;
+    }
+
+    let opts = SslOpts::default()
+        .with_root_cert_path(tls.ca_file.clone())
+        .with_client_identity(tls.cert_file.clone().map(ClientIdentity::new))
+        .with_danger_accept_invalid_certs(tls.mode == TlsMode::Require)
+        .with_danger_skip_domain_validation(tls.mode != TlsMode::VerifyFull);
+    Some(opts)
+}
+
 #[async_trait]
 impl Database for MysqlDatabase {
-    async fn query(&self, _: QueryContext, query: String) -> Box<dyn Display> {
-        Self::execute(&query, Arc::clone(&self.conn)).await
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        let conn = Arc::clone(&self.conn);
+        self.thread.run(move || Self::execute(&query, conn)).await
     }
 }
 
 impl MysqlDatabase {
-    async fn execute(query: &str, connect: Arc<Mutex<Conn>>) -> Box<dyn Display> {
+    fn execute(query: &str, connect: Arc<Mutex<Conn>>) -> QueryResult {
         let mut conn = match connect.lock() {
             Ok(conn) => conn,
-            Err(e) => return Box::new(format!("Failed to get connection, err: {:?}", e)),
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to get connection, err: {:?}",
+                    e
+                )))
+            }
         };
 
-        let result = conn.query_iter(query);
-        Box::new(match result {
-            Ok(result) => {
-                let mut rows = vec![];
-                let affected_rows = result.affected_rows();
-                for row in result {
-                    match row {
-                        Ok(r) => rows.push(r),
-                        Err(e) => {
-                            return Box::new(format!("Failed to parse query result, err: {:?}", e))
-                        }
-                    }
-                }
-
-                if rows.is_empty() {
-                    format!("affected_rows: {}", affected_rows)
-                } else {
-                    format!("{}", MysqlFormatter { rows })
-                }
+        let mut result = match conn.query_iter(query) {
+            Ok(result) => result,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to execute query, err: {:?}",
+                    e
+                )))
             }
-            Err(e) => format!("Failed to execute query, err: {:?}", e),
-        })
-    }
-}
-
-struct MysqlFormatter {
-    pub rows: Vec<Row>,
-}
+        };
 
-impl Display for MysqlFormatter {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let head_column = &self.rows[0];
-        let head_binding = head_column.columns();
-        let names = head_binding
-            .iter()
-            .map(|column| column.name_str())
-            .collect::<Vec<Cow<str>>>();
-        for name in &names {
-            f.write_fmt(format_args!("{},", name))?;
+        let columns = result.columns().as_ref().to_vec();
+        if columns.is_empty() {
+            return QueryResult::AffectedRows(result.affected_rows());
         }
-        f.write_str("\n")?;
 
-        for row in &self.rows {
-            for column_name in &names {
-                let name = column_name.borrow();
-                f.write_fmt(format_args!("{:?},", row.get::<String, &str>(name)))?;
+        let mut rows = Vec::new();
+        for row in result.by_ref() {
+            match row {
+                Ok(row) => rows.push(row.unwrap()),
+                Err(e) => {
+                    return QueryResult::Error(Box::new(format!(
+                        "Failed to parse query result, err: {:?}",
+                        e
+                    )))
+                }
             }
-            f.write_str("\n")?;
         }
 
-        Ok(())
+        QueryResult::ResultSet(to_result_set(&columns, rows))
     }
 }
@@ -0,0 +1,258 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Conversion from MySQL values, shared by sync and async MySQL backends.
+
+#[cfg(feature = "mysql")]
+use mysql::{
+    consts::{ColumnFlags, ColumnType},
+    Column as MysqlColumn, Value as MysqlValue,
+};
+#[cfg(not(feature = "mysql"))]
+use mysql_async::{
+    consts::{ColumnFlags, ColumnType},
+    Column as MysqlColumn, Value as MysqlValue,
+};
+
+use crate::{Column, ResultSet, Value};
+
+/// Character set number of binary strings, like `BLOB` and `BINARY`.
+const BINARY_CHARSET: u16 = 63;
+
+/// Build a result set from columns and rows of one MySQL result set.
+pub(crate) fn to_result_set(columns: &[MysqlColumn], rows: Vec<Vec<MysqlValue>>) -> ResultSet {
+    let mut result_set = ResultSet::new(
+        columns
+            .iter()
+            .map(|column| Column::new(column.name_str(), type_name(column.column_type())))
+            .collect(),
+    );
+    for row in rows {
+        result_set.push_row(
+            row.into_iter()
+                .zip(columns)
+                .map(|(value, column)| convert_value(value, column))
+                .collect(),
+        );
+    }
+
+    result_set
+}
+
+/// Type name without the `MYSQL_TYPE_` prefix, e.g. `LONGLONG`.
+fn type_name(column_type: ColumnType) -> String {
+    let name = format!("{column_type:?}");
+    name.strip_prefix("MYSQL_TYPE_")
+        .map(ToString::to_string)
+        .unwrap_or(name)
+}
+
+/// Convert a MySQL value to sqlness value according to its column.
+///
+/// Values of text protocol are always bytes, which are decoded by the column
+/// type, while values of binary protocol are already typed.
+fn convert_value(value: MysqlValue, column: &MysqlColumn) -> Value {
+    match value {
+        MysqlValue::NULL => Value::Null,
+        MysqlValue::Int(v) => Value::Int(v),
+        MysqlValue::UInt(v) => Value::UInt(v),
+        // Widen via text so `0.1f32` is still rendered as `0.1`.
+        MysqlValue::Float(v) => Value::Float(v.to_string().parse().unwrap_or(f64::from(v))),
+        MysqlValue::Double(v) => Value::Float(v),
+        MysqlValue::Date(year, month, day, hour, minute, second, micros) => {
+            let date = format!("{year:04}-{month:02}-{day:02}");
+            if matches!(
+                column.column_type(),
+                ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE
+            ) {
+                Value::Date(date)
+            } else {
+                Value::Timestamp(format!(
+                    "{date} {hour:02}:{minute:02}:{second:02}{}",
+                    format_micros(micros)
+                ))
+            }
+        }
+        MysqlValue::Time(negative, days, hours, minutes, seconds, micros) => Value::Time(format!(
+            "{}{:02}:{minutes:02}:{seconds:02}{}",
+            if negative { "-" } else { "" },
+            days * 24 + u32::from(hours),
+            format_micros(micros)
+        )),
+        MysqlValue::Bytes(bytes) => convert_bytes(bytes, column),
+    }
+}
+
+fn format_micros(micros: u32) -> String {
+    if micros == 0 {
+        String::new()
+    } else {
+        format!(".{micros:06}")
+    }
+}
+
+fn convert_bytes(bytes: Vec<u8>, column: &MysqlColumn) -> Value {
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => return Value::Bytes(e.into_bytes()),
+    };
+    match column.column_type() {
+        ColumnType::MYSQL_TYPE_TINY
+        | ColumnType::MYSQL_TYPE_SHORT
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_LONGLONG
+        | ColumnType::MYSQL_TYPE_YEAR => {
+            let value = if column.flags().contains(ColumnFlags::UNSIGNED_FLAG) {
+                text.parse().map(Value::UInt).ok()
+            } else {
+                text.parse().map(Value::Int).ok()
+            };
+            value.unwrap_or(Value::String(text))
+        }
+        ColumnType::MYSQL_TYPE_FLOAT | ColumnType::MYSQL_TYPE_DOUBLE => text
+            .parse()
+            .map(Value::Float)
+            .unwrap_or(Value::String(text)),
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => Value::Decimal(text),
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => Value::Date(text),
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => Value::Time(text),
+        ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_DATETIME2
+        | ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2 => Value::Timestamp(text),
+        _ if column.character_set() == BINARY_CHARSET => Value::Bytes(text.into_bytes()),
+        _ => Value::String(text),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UTF8MB4_CHARSET: u16 = 45;
+
+    fn column(column_type: ColumnType) -> MysqlColumn {
+        MysqlColumn::new(column_type).with_character_set(UTF8MB4_CHARSET)
+    }
+
+    fn unsigned(column_type: ColumnType) -> MysqlColumn {
+        column(column_type).with_flags(ColumnFlags::UNSIGNED_FLAG)
+    }
+
+    fn binary(column_type: ColumnType) -> MysqlColumn {
+        MysqlColumn::new(column_type).with_character_set(BINARY_CHARSET)
+    }
+
+    fn bytes(text: &str) -> MysqlValue {
+        MysqlValue::Bytes(text.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn convert_text_values() {
+        use ColumnType::*;
+
+        let cases = [
+            (MysqlValue::NULL, column(MYSQL_TYPE_LONG), "NULL"),
+            (bytes("-128"), column(MYSQL_TYPE_TINY), "-128"),
+            (bytes("65535"), unsigned(MYSQL_TYPE_SHORT), "65535"),
+            (bytes("-8388608"), column(MYSQL_TYPE_INT24), "-8388608"),
+            (bytes("-2147483648"), column(MYSQL_TYPE_LONG), "-2147483648"),
+            (
+                bytes("18446744073709551615"),
+                unsigned(MYSQL_TYPE_LONGLONG),
+                "18446744073709551615",
+            ),
+            (bytes("2024"), unsigned(MYSQL_TYPE_YEAR), "2024"),
+            (bytes("0.1"), column(MYSQL_TYPE_FLOAT), "0.1"),
+            (bytes("-2.5e-3"), column(MYSQL_TYPE_DOUBLE), "-0.0025"),
+            (bytes("1.50"), column(MYSQL_TYPE_NEWDECIMAL), "1.50"),
+            (bytes("2024-01-02"), column(MYSQL_TYPE_DATE), "2024-01-02"),
+            (bytes("-838:59:59"), column(MYSQL_TYPE_TIME), "-838:59:59"),
+            (
+                bytes("2024-01-02 03:04:05.123456"),
+                column(MYSQL_TYPE_DATETIME),
+                "2024-01-02 03:04:05.123456",
+            ),
+            (
+                bytes("2024-01-02 03:04:05"),
+                column(MYSQL_TYPE_TIMESTAMP),
+                "2024-01-02 03:04:05",
+            ),
+            (bytes("hello"), column(MYSQL_TYPE_VAR_STRING), "hello"),
+            (bytes("{\"a\": 1}"), column(MYSQL_TYPE_JSON), "{\"a\": 1}"),
+            (bytes("abc"), binary(MYSQL_TYPE_BLOB), "0x616263"),
+            (
+                MysqlValue::Bytes(vec![0x05]),
+                binary(MYSQL_TYPE_BIT),
+                "0x05",
+            ),
+            (
+                MysqlValue::Bytes(vec![0xff, 0x00]),
+                column(MYSQL_TYPE_STRING),
+                "0xff00",
+            ),
+        ];
+        for (value, column, expected) in cases {
+            let actual = convert_value(value.clone(), &column);
+            assert_eq!(actual.to_string(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn convert_binary_values() {
+        use ColumnType::*;
+
+        let cases = [
+            (MysqlValue::Int(-1), column(MYSQL_TYPE_LONGLONG), "-1"),
+            (
+                MysqlValue::UInt(u64::MAX),
+                unsigned(MYSQL_TYPE_LONGLONG),
+                "18446744073709551615",
+            ),
+            (MysqlValue::Float(0.1), column(MYSQL_TYPE_FLOAT), "0.1"),
+            (MysqlValue::Double(2.5), column(MYSQL_TYPE_DOUBLE), "2.5"),
+            (
+                MysqlValue::Date(2024, 1, 2, 0, 0, 0, 0),
+                column(MYSQL_TYPE_DATE),
+                "2024-01-02",
+            ),
+            (
+                MysqlValue::Date(2024, 1, 2, 3, 4, 5, 0),
+                column(MYSQL_TYPE_DATETIME),
+                "2024-01-02 03:04:05",
+            ),
+            (
+                MysqlValue::Date(2024, 1, 2, 3, 4, 5, 6),
+                column(MYSQL_TYPE_TIMESTAMP),
+                "2024-01-02 03:04:05.000006",
+            ),
+            (
+                MysqlValue::Time(true, 1, 2, 3, 4, 0),
+                column(MYSQL_TYPE_TIME),
+                "-26:03:04",
+            ),
+            (
+                MysqlValue::Time(false, 0, 0, 0, 1, 500000),
+                column(MYSQL_TYPE_TIME),
+                "00:00:01.500000",
+            ),
+        ];
+        for (value, column, expected) in cases {
+            let actual = convert_value(value.clone(), &column);
+            assert_eq!(actual.to_string(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn convert_typed() {
+        assert_eq!(
+            convert_value(bytes("42"), &column(ColumnType::MYSQL_TYPE_LONG)),
+            Value::Int(42)
+        );
+        assert_eq!(
+            convert_value(bytes("1.50"), &column(ColumnType::MYSQL_TYPE_NEWDECIMAL)),
+            Value::Decimal("1.50".to_string())
+        );
+        assert_eq!(type_name(ColumnType::MYSQL_TYPE_LONGLONG), "LONGLONG");
+    }
+}
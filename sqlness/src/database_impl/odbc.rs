@@ -0,0 +1,189 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! ODBC backend based on `odbc-api`, for databases only shipping ODBC
+//! drivers.
+//!
+//! A driver manager (unixODBC on Linux and macOS) is required at build and
+//! run time, and the database is located by
+//! [`DatabaseConfig::connection_string`].
+//!
+//! Values are fetched as text, hence rendered the same way as the driver
+//! converts them to strings.
+
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use async_trait::async_trait;
+use odbc_api::{buffers::TextRowSet, Connection, ConnectionOptions, Cursor, Environment};
+
+use super::blocking::QueryThread;
+use crate::{Column, Database, DatabaseConfig, QueryContext, QueryResult, ResultSet, Value};
+
+/// Rows fetched per round trip.
+const BATCH_SIZE: usize = 256;
+/// Max length of text values, longer ones are truncated by drivers.
+const MAX_TEXT_LEN: usize = 4096;
+
+/// The ODBC environment should be created only once per process, and outlive
+/// all connections.
+static ENV: OnceLock<Environment> = OnceLock::new();
+
+pub struct OdbcDatabase {
+    conn: Arc<Mutex<Connection<'static>>>,
+    thread: QueryThread,
+}
+
+impl OdbcDatabase {
+    pub fn try_new(config: &DatabaseConfig) -> Result<Self, odbc_api::Error> {
+        let env = match ENV.get() {
+            Some(env) => env,
+            None => {
+                let env = Environment::new()?;
+                ENV.get_or_init(|| env)
+            }
+        };
+        let options = ConnectionOptions {
+            login_timeout_sec: config
+                .connect_timeout
+                .map(|timeout| timeout.as_millis().div_ceil(1000) as u32),
+            ..Default::default()
+        };
+        let conn = env.connect_with_connection_string(&Self::connection_string(config), options)?;
+        for statement in &config.init_statements {
+            conn.execute(statement, ())?;
+        }
+
+        Ok(OdbcDatabase {
+            conn: Arc::new(Mutex::new(conn)),
+            thread: QueryThread::default(),
+        })
+    }
+
+    fn connection_string(config: &DatabaseConfig) -> String {
+        let mut connection_string = config
+            .connection_string
+            .clone()
+            .unwrap_or_default()
+            .trim_end_matches(';')
+            .to_string();
+        for (key, value) in [("UID", &config.user), ("PWD", &config.pass)] {
+            if let Some(value) = value {
+                if !connection_string.is_empty() {
+                    connection_string.push(';');
+                }
+                // Braces allow `;` in value, and `}` is escaped by doubling.
+                connection_string.push_str(&format!("{key}={{{}}}", value.replace('}', "}}")));
+            }
+        }
+
+        connection_string
+    }
+
+    pub fn execute(&self, query: &str) -> QueryResult {
+        Self::execute_locked(&self.conn, query)
+    }
+
+    fn execute_locked(conn: &Mutex<Connection<'static>>, query: &str) -> QueryResult {
+        let conn = match conn.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to get connection, err: {:?}",
+                    e
+                )))
+            }
+        };
+
+        Self::execute_inner(&conn, query).unwrap_or_else(|e| {
+            QueryResult::Error(Box::new(format!("Failed to execute query, err: {e}")))
+        })
+    }
+
+    fn execute_inner(conn: &Connection, query: &str) -> Result<QueryResult, odbc_api::Error> {
+        let mut statement = conn.preallocate()?;
+        if let Some(cursor) = statement.execute(query, ())? {
+            return Ok(QueryResult::ResultSet(Self::fetch(cursor)?));
+        }
+
+        let rows = statement.row_count()?.unwrap_or_default();
+        Ok(QueryResult::AffectedRows(rows as u64))
+    }
+
+    fn fetch(mut cursor: impl Cursor) -> Result<ResultSet, odbc_api::Error> {
+        let names = cursor.column_names()?.collect::<Result<Vec<_>, _>>()?;
+        let mut columns = Vec::with_capacity(names.len());
+        for (i, name) in names.into_iter().enumerate() {
+            let data_type = cursor.col_data_type(i as u16 + 1)?;
+            columns.push(Column::new(name, format!("{data_type:?}")));
+        }
+        let mut result_set = ResultSet::new(columns);
+
+        let buffer = TextRowSet::for_cursor(BATCH_SIZE, &mut cursor, Some(MAX_TEXT_LEN))?;
+        let mut cursor = cursor.bind_buffer(buffer)?;
+        while let Some(batch) = cursor.fetch()? {
+            for row in 0..batch.num_rows() {
+                let values = (0..batch.num_cols())
+                    .map(|col| match batch.at(col, row) {
+                        Some(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+                        None => Value::Null,
+                    })
+                    .collect();
+                result_set.push_row(values);
+            }
+        }
+
+        Ok(result_set)
+    }
+}
+
+#[async_trait]
+impl Database for OdbcDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        let conn = Arc::clone(&self.conn);
+        self.thread
+            .run(move || Self::execute_locked(&conn, &query))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DatabaseConfigBuilder;
+
+    #[test]
+    fn build_connection_string() {
+        let config = |connection_string: Option<&str>, user: Option<&str>, pass: Option<&str>| {
+            DatabaseConfigBuilder::default()
+                .ip_or_host(String::new())
+                .tcp_port(0)
+                .user(user.map(String::from))
+                .pass(pass.map(String::from))
+                .db_name(None)
+                .connection_string(connection_string.map(String::from))
+                .build()
+                .unwrap()
+        };
+
+        let cases = [
+            (config(Some("DSN=test;"), None, None), "DSN=test"),
+            (
+                config(Some("Driver={SQLite3};Database=a.db"), Some("root"), None),
+                "Driver={SQLite3};Database=a.db;UID={root}",
+            ),
+            (
+                config(None, Some("root"), Some("p;w}d")),
+                "UID={root};PWD={p;w}}d}",
+            ),
+        ];
+        for (config, expected) in cases {
+            assert_eq!(OdbcDatabase::connection_string(&config), expected);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! A minimal pool of connections for async backends.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::lock::{Mutex, MutexGuard};
+
+/// A fixed number of connections, each used by one query at a time.
+pub(crate) struct Pool<T> {
+    conns: Vec<Mutex<T>>,
+    /// Where to start looking for an idle connection, so connections are
+    /// used in turn.
+    next: AtomicUsize,
+}
+
+impl<T> Pool<T> {
+    /// `conns` must not be empty.
+    pub(crate) fn new(conns: Vec<T>) -> Self {
+        assert!(!conns.is_empty(), "pool needs at least one connection");
+        Self {
+            conns: conns.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get an idle connection, or wait for a busy one if all are busy.
+    pub(crate) async fn get(&self) -> MutexGuard<'_, T> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let len = self.conns.len();
+        for i in 0..len {
+            if let Some(conn) = self.conns[(start + i) % len].try_lock() {
+                return conn;
+            }
+        }
+
+        self.conns[start % len].lock().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn get_idle_first() {
+        let pool = Pool::new(vec![0, 1]);
+        let first = pool.get().await;
+        let second = pool.get().await;
+        assert_ne!(*first, *second);
+
+        drop(first);
+        let third = pool.get().await;
+        assert_ne!(*third, *second);
+    }
+}
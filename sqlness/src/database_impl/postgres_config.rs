@@ -0,0 +1,154 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Connection settings shared by sync and async PostgreSQL backends.
+
+use native_tls::{Certificate, Identity, TlsConnector};
+#[cfg(feature = "postgres")]
+use postgres::Error as DriverError;
+use postgres_native_tls::MakeTlsConnector;
+use thiserror::Error;
+#[cfg(not(feature = "postgres"))]
+use tokio_postgres::Error as DriverError;
+
+use crate::{DatabaseConfig, TlsConfig, TlsMode};
+
+#[derive(Debug, Error)]
+pub enum PostgresError {
+    #[error(transparent)]
+    Driver(#[from] DriverError),
+
+    #[error("Failed to setup TLS, msg:{0}")]
+    Tls(String),
+}
+
+/// Connection string in `key=value` format, as accepted by `Config::from_str`.
+pub(crate) fn connection_string(config: &DatabaseConfig) -> String {
+    let host = match &config.socket {
+        Some(dir) => dir.display().to_string(),
+        None => config.ip_or_host.clone(),
+    };
+    let mut pairs = vec![
+        ("host".to_string(), host),
+        ("port".to_string(), config.tcp_port.to_string()),
+    ];
+    let optional = [
+        ("user", &config.user),
+        ("password", &config.pass),
+        ("dbname", &config.db_name),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            pairs.push((key.to_string(), value.clone()));
+        }
+    }
+    if let Some(timeout) = config.connect_timeout {
+        // Only whole seconds are supported, round up to not time out earlier.
+        let seconds = timeout.as_millis().div_ceil(1000);
+        pairs.push(("connect_timeout".to_string(), seconds.to_string()));
+    }
+    let sslmode = match config.tls.mode {
+        TlsMode::Disable => "disable",
+        // Verification is done by the TLS connector.
+        _ => "require",
+    };
+    pairs.push(("sslmode".to_string(), sslmode.to_string()));
+    pairs.extend(config.params.iter().cloned());
+
+    pairs
+        .iter()
+        .map(|(key, value)| {
+            let value = value.replace('\\', "\\\\").replace('\'', "\\'");
+            format!("{key}='{value}'")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn make_tls(tls: &TlsConfig) -> Result<MakeTlsConnector, PostgresError> {
+    let read = |path: &std::path::Path| {
+        std::fs::read(path)
+            .map_err(|e| PostgresError::Tls(format!("failed to read {}, err:{e}", path.display())))
+    };
+    let tls_err = |e: native_tls::Error| PostgresError::Tls(e.to_string());
+
+    let mut builder = TlsConnector::builder();
+    if let Some(ca_file) = &tls.ca_file {
+        builder.add_root_certificate(Certificate::from_pem(&read(ca_file)?).map_err(tls_err)?);
+    }
+    match (&tls.cert_file, &tls.key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let identity =
+                Identity::from_pkcs8(&read(cert_file)?, &read(key_file)?).map_err(tls_err)?;
+            builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(PostgresError::Tls(
+                "cert_file and key_file should be set together".to_string(),
+            ))
+        }
+    }
+    builder
+        .danger_accept_invalid_certs(tls.mode == TlsMode::Require)
+        .danger_accept_invalid_hostnames(tls.mode != TlsMode::VerifyFull);
+
+    Ok(MakeTlsConnector::new(builder.build().map_err(tls_err)?))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::PathBuf, time::Duration};
+
+    #[cfg(feature = "postgres")]
+    use postgres::Config;
+    #[cfg(not(feature = "postgres"))]
+    use tokio_postgres::Config;
+
+    use super::*;
+    use crate::DatabaseConfigBuilder;
+
+    #[test]
+    fn build_connection_string() {
+        let mut config = DatabaseConfigBuilder::default()
+            .ip_or_host("127.0.0.1".to_string())
+            .tcp_port(5432)
+            .user(Some("postgres".to_string()))
+            .pass(Some("it's".to_string()))
+            .db_name(None)
+            .build()
+            .unwrap();
+        assert_eq!(
+            connection_string(&config),
+            r"host='127.0.0.1' port='5432' user='postgres' password='it\'s' sslmode='disable'"
+        );
+
+        config.socket = Some(PathBuf::from("/var/run/postgresql"));
+        config.connect_timeout = Some(Duration::from_millis(1500));
+        config.tls.mode = TlsMode::VerifyFull;
+        config.params = vec![("application_name".to_string(), "sqlness".to_string())];
+        let connection_string = connection_string(&config);
+        assert_eq!(
+            connection_string,
+            r"host='/var/run/postgresql' port='5432' user='postgres' password='it\'s' connect_timeout='2' sslmode='require' application_name='sqlness'"
+        );
+        assert!(connection_string.parse::<Config>().is_ok());
+    }
+
+    #[test]
+    fn make_tls_with_invalid_files() {
+        let tls = TlsConfig {
+            mode: TlsMode::VerifyFull,
+            ca_file: Some(PathBuf::from("/not/exist/ca.pem")),
+            ..Default::default()
+        };
+        assert!(make_tls(&tls).is_err());
+
+        let tls = TlsConfig {
+            cert_file: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+        assert!(make_tls(&tls).is_err());
+
+        assert!(make_tls(&TlsConfig::default()).is_ok());
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Conversion from PostgreSQL responses, shared by sync and async PostgreSQL
+//! backends.
+
+#[cfg(feature = "postgres")]
+use postgres::{types::Type, SimpleQueryMessage};
+#[cfg(not(feature = "postgres"))]
+use tokio_postgres::{types::Type, SimpleQueryMessage};
+
+use crate::{Column, QueryResult, ResultSet, Value};
+
+/// Convert responses of a simple query to query result, only the result of
+/// last statement is kept.
+///
+/// `types` are column types of the statement if known, values are kept as
+/// text otherwise.
+pub(crate) fn to_query_result(
+    types: Option<Vec<Type>>,
+    messages: Vec<SimpleQueryMessage>,
+) -> QueryResult {
+    let mut result = None;
+    let mut result_set = None;
+    for message in messages {
+        match message {
+            SimpleQueryMessage::RowDescription(columns) => {
+                let columns = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        let ty = types.as_ref().and_then(|types| types.get(i));
+                        Column::new(column.name(), ty.map_or("text", Type::name))
+                    })
+                    .collect();
+                result_set = Some(ResultSet::new(columns));
+            }
+            SimpleQueryMessage::Row(row) => {
+                if let Some(result_set) = &mut result_set {
+                    let values = (0..row.len())
+                        .map(|i| {
+                            let ty = types.as_ref().and_then(|types| types.get(i));
+                            convert_value(row.get(i), ty)
+                        })
+                        .collect();
+                    result_set.push_row(values);
+                }
+            }
+            SimpleQueryMessage::CommandComplete(rows) => {
+                result = Some(match result_set.take() {
+                    Some(result_set) => QueryResult::ResultSet(result_set),
+                    None => QueryResult::AffectedRows(rows),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    result.unwrap_or_else(|| QueryResult::Output(Box::new("(Empty response)")))
+}
+
+/// Convert a value in Postgres text format to sqlness value according to its
+/// type, values of unknown or other types are kept as text.
+fn convert_value(text: Option<&str>, ty: Option<&Type>) -> Value {
+    let Some(text) = text else {
+        return Value::Null;
+    };
+    let text = text.to_string();
+    match ty.map(Type::name) {
+        Some("bool") => match text.as_str() {
+            "t" => Value::Bool(true),
+            "f" => Value::Bool(false),
+            _ => Value::String(text),
+        },
+        Some("int2" | "int4" | "int8" | "oid") => {
+            text.parse().map(Value::Int).unwrap_or(Value::String(text))
+        }
+        // Keep `NaN` and `Infinity` as is.
+        Some("float4" | "float8") => match text.parse::<f64>() {
+            Ok(v) if v.is_finite() => Value::Float(v),
+            _ => Value::String(text),
+        },
+        Some("numeric") => Value::Decimal(text),
+        Some("date") => Value::Date(text),
+        Some("time" | "timetz") => Value::Time(text),
+        Some("timestamp" | "timestamptz") => Value::Timestamp(text),
+        Some("bytea") => decode_bytea(&text).map_or(Value::String(text), Value::Bytes),
+        _ => Value::String(text),
+    }
+}
+
+/// Decode bytea in hex format, e.g. `\x0aff`.
+fn decode_bytea(text: &str) -> Option<Vec<u8>> {
+    let hex = text.strip_prefix("\\x")?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_values() {
+        let cases = [
+            (None, Some(Type::INT4), "NULL"),
+            (Some("t"), Some(Type::BOOL), "true"),
+            (Some("-32768"), Some(Type::INT2), "-32768"),
+            (
+                Some("9223372036854775807"),
+                Some(Type::INT8),
+                "9223372036854775807",
+            ),
+            (Some("0.1"), Some(Type::FLOAT4), "0.1"),
+            (Some("Infinity"), Some(Type::FLOAT8), "Infinity"),
+            (Some("NaN"), Some(Type::FLOAT8), "NaN"),
+            (Some("12345.6700"), Some(Type::NUMERIC), "12345.6700"),
+            (Some("2024-01-02"), Some(Type::DATE), "2024-01-02"),
+            (Some("03:04:05.123"), Some(Type::TIME), "03:04:05.123"),
+            (
+                Some("2024-01-02 03:04:05+00"),
+                Some(Type::TIMESTAMPTZ),
+                "2024-01-02 03:04:05+00",
+            ),
+            (Some("{1,2,NULL}"), Some(Type::INT4_ARRAY), "{1,2,NULL}"),
+            (Some("{\"a\": 1}"), Some(Type::JSONB), "{\"a\": 1}"),
+            (
+                Some("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"),
+                Some(Type::UUID),
+                "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11",
+            ),
+            (Some("\\x00ab"), Some(Type::BYTEA), "0x00ab"),
+            (
+                Some("1 day 02:00:00"),
+                Some(Type::INTERVAL),
+                "1 day 02:00:00",
+            ),
+            (Some("42"), None, "42"),
+        ];
+        for (text, ty, expected) in cases {
+            let actual = convert_value(text, ty.as_ref());
+            assert_eq!(actual.to_string(), expected, "{text:?}, {ty:?}");
+        }
+    }
+
+    #[test]
+    fn convert_typed() {
+        assert_eq!(convert_value(Some("42"), Some(&Type::INT8)), Value::Int(42));
+        assert_eq!(convert_value(Some("42"), None), Value::from("42"));
+        assert_eq!(decode_bytea("\\x0aff"), Some(vec![0x0a, 0xff]));
+        assert_eq!(decode_bytea("\\x0"), None);
+        assert_eq!(decode_bytea("abc"), None);
+    }
+}
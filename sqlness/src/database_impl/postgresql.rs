@@ -1,92 +1,80 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
 use async_trait::async_trait;
-use postgres::{Client, Config, NoTls, Row};
+use postgres::{Client, Config};
 use std::{
     fmt::Display,
     sync::{Arc, Mutex},
 };
 
-use crate::{Database, DatabaseConfig, QueryContext};
+pub use super::postgres_config::PostgresError;
+use super::{
+    blocking::QueryThread,
+    postgres_config::{connection_string, make_tls},
+    postgres_value::to_query_result,
+};
+use crate::{Database, DatabaseConfig, QueryContext, QueryResult};
 
 pub struct PostgresqlDatabase {
     client: Arc<Mutex<Client>>,
+    thread: QueryThread,
 }
 
 impl PostgresqlDatabase {
-    pub fn try_new(config: &DatabaseConfig) -> Result<Self, postgres::Error> {
-        let mut postgres_config = Config::new();
-        postgres_config
-            .port(config.tcp_port)
-            .host(&config.ip_or_host);
-
-        if let Some(user) = &config.user {
-            postgres_config.user(user);
-        }
-        if let Some(password) = &config.pass {
-            postgres_config.password(password);
+    pub fn try_new(config: &DatabaseConfig) -> Result<Self, PostgresError> {
+        let postgres_config = connection_string(config).parse::<Config>()?;
+        let mut client = postgres_config.connect(make_tls(&config.tls)?)?;
+        for statement in &config.init_statements {
+            client.batch_execute(statement)?;
         }
-        if let Some(dbname) = &config.db_name {
-            postgres_config.dbname(dbname);
-        }
-        let client = postgres_config.connect(NoTls)?;
         Ok(PostgresqlDatabase {
             client: Arc::new(Mutex::new(client)),
+            thread: QueryThread::default(),
         })
     }
 
-    pub fn execute(query: &str, client: Arc<Mutex<Client>>) -> Box<dyn Display> {
+    pub fn execute(query: &str, client: Arc<Mutex<Client>>) -> QueryResult {
         let mut client = match client.lock() {
             Ok(client) => client,
             Err(err) => {
-                return Box::new(format!("Failed to get connection, encountered: {:?}", err))
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to get connection, encountered: {:?}",
+                    err
+                )))
             }
         };
 
-        let result = match client.query(query, &[]) {
-            Ok(rows) => {
-                format!("{}", PostgresqlFormatter { rows })
+        // Column types are only known by preparing the statement, which fails
+        // when the query contains multiple statements, then values are kept as text.
+        let types = client.prepare(query).ok().map(|statement| {
+            statement
+                .columns()
+                .iter()
+                .map(|column| column.type_().clone())
+                .collect::<Vec<_>>()
+        });
+        let messages = match client.simple_query(query) {
+            Ok(messages) => messages,
+            Err(err) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to execute query, encountered: {:?}",
+                    err
+                )))
             }
-            Err(err) => format!("Failed to execute query, encountered: {:?}", err),
         };
 
-        Box::new(result)
-    }
-}
-
-struct PostgresqlFormatter {
-    pub rows: Vec<Row>,
-}
-
-impl Display for PostgresqlFormatter {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.rows.is_empty() {
-            return f.write_fmt(format_args!("(Empty response)"));
-        }
-
-        let top = &self.rows[0];
-        let columns = top
-            .columns()
-            .iter()
-            .map(|column| column.name())
-            .collect::<Vec<_>>();
-        for col in &columns {
-            f.write_fmt(format_args!("{},", col))?;
-        }
-
-        f.write_str("\n")?;
-
-        for row in &self.rows {
-            f.write_fmt(format_args!("{:?}\n", row))?;
-        }
-
-        Ok(())
+        to_query_result(types, messages)
     }
 }
 
 #[async_trait]
 impl Database for PostgresqlDatabase {
-    async fn query(&self, _: QueryContext, query: String) -> Box<dyn Display> {
-        Self::execute(&query, Arc::clone(&self.client))
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        let client = Arc::clone(&self.client);
+        self.thread.run(move || Self::execute(&query, client)).await
     }
 }
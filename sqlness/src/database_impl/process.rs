@@ -0,0 +1,432 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Backend for databases only reachable through their own REPL binary.
+//!
+//! The command is spawned once when [`ProcessDatabase`] is created, usually
+//! in [`EnvController::start`](crate::EnvController::start), and killed when
+//! it's dropped. Each query is written to its stdin, followed by a newline,
+//! then its stdout is read until [`ProcessConfig::terminator`] matches.
+//!
+//! REPLs printing a prompt can use the prompt as terminator. As most of them
+//! don't print prompts when stdin is not a terminal, a
+//! [`ProcessConfig::sentinel`] can be sent after each query instead, whose
+//! output is the terminator.
+//!
+//! Stderr of a query fails it when it matches [`ProcessConfig::error_pattern`],
+//! otherwise it's appended to the output, like warnings.
+//!
+//! The process is killed if a response doesn't end in time, and following
+//! queries fail.
+
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use derive_builder::Builder;
+use regex::Regex;
+
+use super::blocking::QueryThread;
+use crate::{Database, QueryContext, QueryResult};
+
+/// Config of [`ProcessDatabase`].
+#[derive(Debug, Builder, Clone)]
+pub struct ProcessConfig {
+    pub program: String,
+    /// Default value: none
+    #[builder(default = "ProcessConfig::default_args()")]
+    pub args: Vec<String>,
+    /// Working directory of the process.
+    /// Default value: `None`, inherit the current one
+    #[builder(default = "ProcessConfig::default_current_dir()")]
+    pub current_dir: Option<PathBuf>,
+    /// Regex matching the end of a response in stdout, like a prompt
+    /// `sqlite> $`, or output of the sentinel. What precedes the match is the
+    /// response.
+    pub terminator: String,
+    /// Command sent after each query to make the terminator printed, e.g.
+    /// `SELECT '__END__';`. When it's `None`, the terminator is expected to
+    /// be a prompt, which is also awaited once after the process starts.
+    /// Default value: `None`
+    #[builder(default = "ProcessConfig::default_sentinel()")]
+    pub sentinel: Option<String>,
+    /// Max time to wait for the terminator, when the query has no timeout.
+    /// Default value: 30s
+    #[builder(default = "ProcessConfig::default_read_timeout()")]
+    pub read_timeout: Duration,
+    /// Regex matching stderr of a failed query, like `^(Error|ERROR)`. When
+    /// it's `None`, any stderr fails the query.
+    /// Default value: `None`
+    #[builder(default = "ProcessConfig::default_error_pattern()")]
+    pub error_pattern: Option<String>,
+}
+
+impl ProcessConfig {
+    fn default_args() -> Vec<String> {
+        Vec::new()
+    }
+
+    fn default_current_dir() -> Option<PathBuf> {
+        None
+    }
+
+    fn default_sentinel() -> Option<String> {
+        None
+    }
+
+    fn default_read_timeout() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_error_pattern() -> Option<String> {
+        None
+    }
+}
+
+/// Max time stderr of a query is awaited after its stdout ends.
+const STDERR_DELAY: Duration = Duration::from_millis(20);
+
+/// Output of the process, stdout and stderr are read by separate threads.
+enum Chunk {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// The process, with compiled patterns of its config.
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    output: Receiver<Chunk>,
+    terminator: Regex,
+    error_pattern: Option<Regex>,
+    sentinel: Option<String>,
+    read_timeout: Duration,
+}
+
+impl Session {
+    fn execute(&mut self, query: &str, timeout: Option<Duration>) -> QueryResult {
+        // Discard unexpected output between queries.
+        while self.output.try_recv().is_ok() {}
+
+        let mut input = format!("{query}\n");
+        if let Some(sentinel) = &self.sentinel {
+            input.push_str(&format!("{sentinel}\n"));
+        }
+        if let Err(e) = self
+            .stdin
+            .write_all(input.as_bytes())
+            .and_then(|_| self.stdin.flush())
+        {
+            return QueryResult::Error(Box::new(format!("Failed to write query, err: {e}")));
+        }
+
+        let (stdout, stderr) = match self.read_response(timeout.unwrap_or(self.read_timeout)) {
+            Ok(response) => response,
+            Err(e) => return QueryResult::Error(Box::new(e)),
+        };
+        let (stdout, stderr) = (stdout.trim_end(), stderr.trim_end());
+        if stderr.trim().is_empty() {
+            return QueryResult::Output(Box::new(stdout.to_string()));
+        }
+
+        let output = if stdout.is_empty() {
+            stderr.to_string()
+        } else {
+            format!("{stdout}\n{stderr}")
+        };
+        let failed = self
+            .error_pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(stderr));
+        if failed {
+            QueryResult::Error(Box::new(output))
+        } else {
+            QueryResult::Output(Box::new(output))
+        }
+    }
+
+    /// Read until the terminator matches stdout, return the stdout before it
+    /// and all stderr.
+    fn read_response(&mut self, timeout: Duration) -> Result<(String, String), String> {
+        let deadline = Instant::now() + timeout;
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        loop {
+            let text = String::from_utf8_lossy(&stdout);
+            if let Some(found) = self.terminator.find(&text) {
+                let response = text[..found.start()].to_string();
+                // Stderr may be a bit behind stdout.
+                while let Ok(chunk) = self.output.recv_timeout(STDERR_DELAY) {
+                    if let Chunk::Stderr(bytes) = chunk {
+                        stderr.extend(bytes);
+                    }
+                }
+                return Ok((response, String::from_utf8_lossy(&stderr).into_owned()));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.output.recv_timeout(remaining) {
+                Ok(Chunk::Stdout(bytes)) => stdout.extend(bytes),
+                Ok(Chunk::Stderr(bytes)) => stderr.extend(bytes),
+                Err(RecvTimeoutError::Timeout) => {
+                    // Output of this query would be mistaken as the response
+                    // of following ones, so the process is not usable anymore.
+                    let _ = self.child.kill();
+                    return Err(format!(
+                        "Terminator not found in {timeout:?}, process is killed, output: {}",
+                        String::from_utf8_lossy(&stdout)
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = self.child.wait().map_err(|e| e.to_string())?;
+                    return Err(format!(
+                        "Process exited with {status}, output: {}{}",
+                        String::from_utf8_lossy(&stdout),
+                        String::from_utf8_lossy(&stderr)
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Database behind a REPL process, see [module level document](self).
+pub struct ProcessDatabase {
+    session: Arc<Mutex<Session>>,
+    thread: QueryThread,
+}
+
+impl ProcessDatabase {
+    pub fn try_new(config: ProcessConfig) -> Result<Self, String> {
+        let terminator = Regex::new(&config.terminator)
+            .map_err(|e| format!("Invalid terminator {}, err: {e}", config.terminator))?;
+        let error_pattern = config
+            .error_pattern
+            .as_deref()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| format!("Invalid error pattern {pattern}, err: {e}"))
+            })
+            .transpose()?;
+
+        let mut command = Command::new(&config.program);
+        command
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &config.current_dir {
+            command.current_dir(dir);
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to spawn {}, err: {e}", config.program))?;
+
+        let (sender, output) = mpsc::channel();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        spawn_reader(stdout, sender.clone(), Chunk::Stdout);
+        spawn_reader(stderr, sender, Chunk::Stderr);
+        let stdin = child.stdin.take().expect("stdin is piped");
+
+        let mut session = Session {
+            child,
+            stdin,
+            output,
+            terminator,
+            error_pattern,
+            sentinel: config.sentinel,
+            read_timeout: config.read_timeout,
+        };
+        if session.sentinel.is_none() {
+            session.read_response(session.read_timeout)?;
+        }
+
+        Ok(ProcessDatabase {
+            session: Arc::new(Mutex::new(session)),
+            thread: QueryThread::default(),
+        })
+    }
+
+    pub fn execute(&self, query: &str, timeout: Option<Duration>) -> QueryResult {
+        Self::execute_locked(&self.session, query, timeout)
+    }
+
+    fn execute_locked(
+        session: &Mutex<Session>,
+        query: &str,
+        timeout: Option<Duration>,
+    ) -> QueryResult {
+        match session.lock() {
+            Ok(mut session) => session.execute(query, timeout),
+            Err(e) => QueryResult::Error(Box::new(format!("Failed to get process, err: {:?}", e))),
+        }
+    }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(
+    mut reader: R,
+    sender: mpsc::Sender<Chunk>,
+    chunk: fn(Vec<u8>) -> Chunk,
+) {
+    std::thread::spawn(move || {
+        let mut buf = [0; 4096];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if sender.send(chunk(buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+// The process is killed after the running query, if any, returns.
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[async_trait]
+impl Database for ProcessDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, context: QueryContext, query: String) -> QueryResult {
+        let session = Arc::clone(&self.session);
+        self.thread
+            .run(move || Self::execute_locked(&session, &query, context.timeout))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn shell(script: &str) -> ProcessConfigBuilder {
+        let mut builder = ProcessConfigBuilder::default();
+        builder
+            .program("sh".to_string())
+            .args(vec!["-c".to_string(), script.to_string()]);
+        builder
+    }
+
+    #[test]
+    fn execute_with_sentinel() {
+        let config = shell("exec sh")
+            .terminator("__END__\n".to_string())
+            .sentinel(Some("echo __END__".to_string()))
+            .build()
+            .unwrap();
+        let db = ProcessDatabase::try_new(config).unwrap();
+
+        let result = db.execute("echo a; echo b", None);
+        assert!(!result.is_error());
+        assert_eq!(result.to_string(), "a\nb");
+
+        let result = db.execute("echo oops >&2", None);
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "oops");
+
+        let result = db.execute("echo a; echo oops >&2", None);
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "a\noops");
+
+        let result = db.execute("exit 3", None);
+        assert!(result.is_error());
+        assert!(result.to_string().starts_with("Process exited"));
+    }
+
+    #[test]
+    fn execute_with_error_pattern() {
+        let config = shell("exec sh")
+            .terminator("__END__\n".to_string())
+            .sentinel(Some("echo __END__".to_string()))
+            .error_pattern(Some("^Error".to_string()))
+            .build()
+            .unwrap();
+        let db = ProcessDatabase::try_new(config).unwrap();
+
+        let result = db.execute("echo a; echo 'Warning: w' >&2", None);
+        assert!(!result.is_error());
+        assert_eq!(result.to_string(), "a\nWarning: w");
+
+        let result = db.execute("echo a; echo 'Error: e' >&2", None);
+        assert!(result.is_error());
+        assert_eq!(result.to_string(), "a\nError: e");
+
+        let config = shell("exec sh")
+            .terminator("__END__\n".to_string())
+            .error_pattern(Some("(".to_string()))
+            .build()
+            .unwrap();
+        assert!(ProcessDatabase::try_new(config)
+            .err()
+            .unwrap()
+            .starts_with("Invalid error pattern"));
+    }
+
+    #[tokio::test]
+    async fn query_off_the_executor() {
+        let config = shell("exec sh")
+            .terminator("__END__\n".to_string())
+            .sentinel(Some("echo __END__".to_string()))
+            .build()
+            .unwrap();
+        let db = ProcessDatabase::try_new(config).unwrap();
+
+        // The timer fires while the query blocks its own thread.
+        let context = QueryContext {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let query = db.query_result(context, "sleep 5".to_string());
+        assert!(crate::timer::timeout(Duration::from_millis(50), query)
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn kill_after_timeout() {
+        let config = shell("exec sh")
+            .terminator("__END__\n".to_string())
+            .sentinel(Some("echo __END__".to_string()))
+            .build()
+            .unwrap();
+        let db = ProcessDatabase::try_new(config).unwrap();
+
+        let result = db.execute("sleep 1", Some(Duration::from_millis(100)));
+        assert!(result.is_error());
+        assert!(result.to_string().starts_with("Terminator not found"));
+
+        let result = db.execute("echo a", None);
+        assert!(result.is_error());
+    }
+
+    #[test]
+    fn execute_with_prompt() {
+        let config =
+            shell(r#"echo banner; printf "> "; while read l; do eval "$l"; printf "> "; done"#)
+                .terminator("> $".to_string())
+                .build()
+                .unwrap();
+        let db = ProcessDatabase::try_new(config).unwrap();
+
+        assert_eq!(db.execute("echo 1", None).to_string(), "1");
+        assert_eq!(db.execute("true", None).to_string(), "");
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! SQLite backend based on `rusqlite`, with SQLite compiled in.
+
+use std::{
+    fmt::Display,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use rusqlite::{types::ValueRef, Connection};
+
+use super::blocking::QueryThread;
+use crate::{Column, Database, QueryContext, QueryResult, ResultSet, Value};
+
+/// Path that opens an in-memory database.
+pub const IN_MEMORY: &str = ":memory:";
+
+/// A SQLite database stored in a file, or in memory which is discarded when
+/// dropped.
+pub struct SqliteDatabase {
+    conn: Arc<Mutex<Connection>>,
+    thread: QueryThread,
+}
+
+impl SqliteDatabase {
+    /// Open the database at `path`, which is created if not exists. Open an
+    /// in-memory database if `path` is [`IN_MEMORY`].
+    pub fn try_new<P: AsRef<Path>>(path: P) -> Result<Self, rusqlite::Error> {
+        let conn = if path.as_ref() == Path::new(IN_MEMORY) {
+            Connection::open_in_memory()?
+        } else {
+            Connection::open(path)?
+        };
+
+        Ok(SqliteDatabase {
+            conn: Arc::new(Mutex::new(conn)),
+            thread: QueryThread::default(),
+        })
+    }
+
+    pub fn in_memory() -> Result<Self, rusqlite::Error> {
+        Self::try_new(IN_MEMORY)
+    }
+
+    pub fn execute(&self, query: &str) -> QueryResult {
+        Self::execute_locked(&self.conn, query)
+    }
+
+    fn execute_locked(conn: &Mutex<Connection>, query: &str) -> QueryResult {
+        let conn = match conn.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                return QueryResult::Error(Box::new(format!(
+                    "Failed to get connection, err: {:?}",
+                    e
+                )))
+            }
+        };
+
+        Self::execute_inner(&conn, query).unwrap_or_else(|e| {
+            QueryResult::Error(Box::new(format!("Failed to execute query, err: {e}")))
+        })
+    }
+
+    fn execute_inner(conn: &Connection, query: &str) -> rusqlite::Result<QueryResult> {
+        let mut statement = conn.prepare(query)?;
+        if statement.column_count() == 0 {
+            // `changes()` isn't reset by statements other than DML, like
+            // DDL, whose affected rows are 0 as the total doesn't change.
+            let before = total_changes(conn)?;
+            let rows = statement.execute([])?;
+            let rows = if total_changes(conn)? == before {
+                0
+            } else {
+                rows as u64
+            };
+            return Ok(QueryResult::AffectedRows(rows));
+        }
+
+        let mut result_set = ResultSet::new(
+            statement
+                .columns()
+                .iter()
+                .map(|column| Column::new(column.name(), column.decl_type().unwrap_or_default()))
+                .collect(),
+        );
+        let column_count = statement.column_count();
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let values = (0..column_count)
+                .map(|i| row.get_ref(i).map(convert_value))
+                .collect::<rusqlite::Result<_>>()?;
+            result_set.push_row(values);
+        }
+
+        Ok(QueryResult::ResultSet(result_set))
+    }
+}
+
+/// Number of rows changed by DML since the connection is opened.
+fn total_changes(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT total_changes()", [], |row| row.get(0))
+}
+
+fn convert_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(v) => Value::Int(v),
+        ValueRef::Real(v) => Value::Float(v),
+        ValueRef::Text(v) => Value::String(String::from_utf8_lossy(v).into_owned()),
+        ValueRef::Blob(v) => Value::Bytes(v.to_vec()),
+    }
+}
+
+#[async_trait]
+impl Database for SqliteDatabase {
+    async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+        Box::new(self.query_result(context, query).await)
+    }
+
+    async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+        let conn = Arc::clone(&self.conn);
+        self.thread
+            .run(move || Self::execute_locked(&conn, &query))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn execute_in_memory() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let result = db.execute("CREATE TABLE t (id INTEGER, name TEXT, score REAL, data BLOB);");
+        assert_eq!(result.to_string(), "affected_rows: 0");
+
+        let result = db.execute("INSERT INTO t VALUES (1, 'a', 0.5, x'0aff'), (2, NULL, 1, NULL);");
+        assert_eq!(result.to_string(), "affected_rows: 2");
+
+        let QueryResult::ResultSet(result_set) = db.execute("SELECT * FROM t ORDER BY id;") else {
+            panic!("expect result set");
+        };
+        assert_eq!(
+            result_set.columns,
+            vec![
+                Column::new("id", "INTEGER"),
+                Column::new("name", "TEXT"),
+                Column::new("score", "REAL"),
+                Column::new("data", "BLOB"),
+            ]
+        );
+        assert_eq!(
+            result_set.rows,
+            vec![
+                vec![
+                    Value::Int(1),
+                    Value::from("a"),
+                    Value::Float(0.5),
+                    Value::Bytes(vec![0x0a, 0xff]),
+                ],
+                vec![Value::Int(2), Value::Null, Value::Float(1.0), Value::Null],
+            ]
+        );
+
+        assert!(db.execute("SELECT * FROM not_exist;").is_error());
+    }
+
+    #[test]
+    fn ddl_affects_no_rows() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        db.execute("CREATE TABLE t (id INTEGER);");
+        let result = db.execute("INSERT INTO t VALUES (1), (2);");
+        assert_eq!(result.to_string(), "affected_rows: 2");
+
+        let result = db.execute("CREATE TABLE t2 (id INTEGER);");
+        assert_eq!(result.to_string(), "affected_rows: 0");
+        let result = db.execute("DROP TABLE t2;");
+        assert_eq!(result.to_string(), "affected_rows: 0");
+        let result = db.execute("DELETE FROM t WHERE id > 5;");
+        assert_eq!(result.to_string(), "affected_rows: 0");
+        let result = db.execute("UPDATE t SET id = 3 WHERE id = 1;");
+        assert_eq!(result.to_string(), "affected_rows: 1");
+    }
+
+    #[test]
+    fn execute_in_file() {
+        let path = std::env::temp_dir().join(format!("sqlness-sqlite-{}.db", std::process::id()));
+        let db = SqliteDatabase::try_new(&path).unwrap();
+        db.execute("CREATE TABLE t (id INTEGER);");
+        db.execute("INSERT INTO t VALUES (1);");
+        drop(db);
+
+        let db = SqliteDatabase::try_new(&path).unwrap();
+        let result = db.execute("SELECT count(*) AS c FROM t;");
+        assert!(result.to_string().contains("| 1 |"));
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
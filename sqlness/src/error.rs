@@ -12,12 +12,29 @@ pub enum SqlnessError {
         path: PathBuf,
     },
 
+    #[error("Unable to write to path {path}")]
+    WritePath {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    /// Error at a 1-based line of a case file, like invalid interceptors.
+    #[error("{}:{line}: {source}", .path.display())]
+    ParseCase {
+        source: Box<SqlnessError>,
+        path: PathBuf,
+        line: usize,
+    },
+
     #[error("Failed to parse toml file {file}, error: {source}")]
     ParseToml {
         source: toml::de::Error,
         file: PathBuf,
     },
 
+    #[error("Invalid config in {file}, msg:{msg}.")]
+    InvalidEnvConfig { file: PathBuf, msg: String },
+
     #[error("IO operation failed, source error: {0}")]
     IO(#[from] std::io::Error),
 
@@ -27,6 +44,9 @@ pub enum SqlnessError {
     #[error("Run failed. {count} cases can't pass")]
     RunFailed { count: usize },
 
+    #[error("Run failed in {} environment(s): {}", .errors.len(), format_env_errors(.errors))]
+    EnvRunFailed { errors: Vec<(String, SqlnessError)> },
+
     #[error("Invalid regexp, source error: {0}")]
     Regex(#[from] regex::Error),
 
@@ -38,6 +58,20 @@ pub enum SqlnessError {
 
     #[error("Missing interceptor prefix, line:{line}.")]
     MissingPrefix { line: String },
+
+    #[error("Unterminated statement.")]
+    UnterminatedStatement,
+
+    #[error("Unknown splitter, name:{name}.")]
+    UnknownSplitter { name: String },
+}
+
+fn format_env_errors(errors: &[(String, SqlnessError)]) -> String {
+    errors
+        .iter()
+        .map(|(env, e)| format!("{env}: {e}"))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 pub(crate) type Result<T> = std::result::Result<T, SqlnessError>;
@@ -0,0 +1,185 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Styles to render [`ResultSet`] in result files.
+
+use std::{fmt::Write, str::FromStr};
+
+use crate::{database::QueryResult, result_set::ResultSet, Value};
+
+/// How [`ResultSet`]s are rendered in result files.
+///
+/// It's chosen per environment by the `format` key under `[sqlness]` table in
+/// the environment's config file, or per query by the `FORMAT` interceptor.
+/// Results not returned as [`ResultSet`] are always rendered as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// ASCII box table, the canonical style.
+    #[default]
+    Table,
+    /// Comma-separated values with a header line, quoted as RFC 4180.
+    Csv,
+    /// Tab-separated values with a header line, special characters escaped
+    /// by backslash.
+    Tsv,
+    /// One JSON object per row, keys in column order.
+    JsonLines,
+    /// One value per line as sqllogictest does, without header.
+    SqlLogicTest,
+}
+
+impl OutputFormat {
+    /// Names accepted by [`OutputFormat::from_str`].
+    pub const NAMES: [&'static str; 5] = ["table", "csv", "tsv", "jsonl", "sqllogictest"];
+
+    pub fn render(&self, result_set: &ResultSet) -> String {
+        match self {
+            OutputFormat::Table => result_set.to_string(),
+            OutputFormat::Csv => render_separated(result_set, ',', escape_csv),
+            OutputFormat::Tsv => render_separated(result_set, '\t', escape_tsv),
+            OutputFormat::JsonLines => render_json_lines(result_set),
+            OutputFormat::SqlLogicTest => render_sqllogictest(result_set),
+        }
+    }
+
+    /// Render the query result, only [`QueryResult::ResultSet`] is affected
+    /// by format.
+    pub(crate) fn render_result(&self, result: &QueryResult) -> String {
+        match result {
+            QueryResult::ResultSet(result_set) => self.render(result_set),
+            result => result.to_string(),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            "sqllogictest" => Ok(OutputFormat::SqlLogicTest),
+            other => Err(format!(
+                "Unknown format `{other}`, expect one of {}",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+fn render_separated(result_set: &ResultSet, separator: char, escape: fn(&str) -> String) -> String {
+    let header = result_set.columns.iter().map(|column| escape(&column.name));
+    let rows = result_set
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|value| escape(&value.to_string())));
+
+    std::iter::once(header.collect::<Vec<_>>())
+        .chain(rows.map(Iterator::collect))
+        .map(|cells| cells.join(&separator.to_string()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_csv(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn escape_tsv(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn render_json_lines(result_set: &ResultSet) -> String {
+    let mut output = String::new();
+    for (i, row) in result_set.rows.iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        output.push('{');
+        for (j, (column, value)) in result_set.columns.iter().zip(row).enumerate() {
+            if j > 0 {
+                output.push(',');
+            }
+            let value = match value {
+                Value::Null => serde_json::Value::Null,
+                Value::Bool(v) => (*v).into(),
+                Value::Int(v) => (*v).into(),
+                Value::UInt(v) => (*v).into(),
+                // NaN and infinity are not valid JSON numbers.
+                Value::Float(v) if v.is_finite() => (*v).into(),
+                value => value.to_string().into(),
+            };
+            let _ = write!(
+                output,
+                "{}:{value}",
+                serde_json::Value::from(column.name.as_str())
+            );
+        }
+        output.push('}');
+    }
+
+    output
+}
+
+fn render_sqllogictest(result_set: &ResultSet) -> String {
+    result_set
+        .rows
+        .iter()
+        .flatten()
+        .map(|value| match value {
+            Value::String(v) if v.is_empty() => "(empty)".to_string(),
+            value => value.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Column;
+
+    fn result_set() -> ResultSet {
+        let mut result =
+            ResultSet::new(vec![Column::new("id", "INT"), Column::new("name", "TEXT")]);
+        result.push_row(vec![Value::Int(1), Value::from("a,\"b\"")]);
+        result.push_row(vec![Value::Float(f64::NAN), Value::from("")]);
+        result.push_row(vec![Value::Null, Value::from("x\ty")]);
+        result
+    }
+
+    #[test]
+    fn parse_format() {
+        for name in OutputFormat::NAMES {
+            assert!(name.parse::<OutputFormat>().is_ok());
+        }
+        assert_eq!("CSV".parse(), Ok(OutputFormat::Csv));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn render_styles() {
+        let result = result_set();
+        let cases = [
+            (OutputFormat::Csv, "id,name\n1,\"a,\"\"b\"\"\"\nNaN,\nNULL,x\ty"),
+            (OutputFormat::Tsv, "id\tname\n1\ta,\"b\"\nNaN\t\nNULL\tx\\ty"),
+            (
+                OutputFormat::JsonLines,
+                "{\"id\":1,\"name\":\"a,\\\"b\\\"\"}\n{\"id\":\"NaN\",\"name\":\"\"}\n{\"id\":null,\"name\":\"x\\ty\"}",
+            ),
+            (OutputFormat::SqlLogicTest, "1\na,\"b\"\nNaN\n(empty)\nNULL\nx\ty"),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(format.render(&result), expected, "{format:?}");
+        }
+    }
+}
@@ -2,24 +2,31 @@
 
 //! Query interceptor implementations.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
     case::QueryContext,
     error::Result,
     error::SqlnessError,
     interceptor::{
-        arg::ArgInterceptorFactory, env::EnvInterceptorFactory, replace::ReplaceInterceptorFactory,
+        arg::ArgInterceptorFactory, env::EnvInterceptorFactory,
+        expect_error::ExpectErrorInterceptorFactory, format::FormatInterceptorFactory,
+        replace::ReplaceInterceptorFactory, retry::RetryInterceptorFactory,
         sort_result::SortResultInterceptorFactory, template::TemplateInterceptorFactory,
+        timeout::TimeoutInterceptorFactory,
     },
 };
 
 pub mod arg;
 pub mod env;
+pub mod expect_error;
+pub mod format;
 pub mod replace;
+pub mod retry;
 pub mod sleep;
 pub mod sort_result;
 pub mod template;
+pub mod timeout;
 
 pub type InterceptorRef = Box<dyn Interceptor + Send + Sync>;
 
@@ -86,6 +93,16 @@ impl Registry {
     }
 }
 
+/// Parse duration like `1s500ms` for interceptor with given prefix.
+///
+/// See detailed format in [duration_str](https://docs.rs/duration-str/0.11.2/duration_str/) crate.
+pub(crate) fn parse_duration(prefix: &str, ctx: &str) -> Result<Duration> {
+    duration_str::parse(ctx).map_err(|e| SqlnessError::InvalidContext {
+        prefix: prefix.to_string(),
+        msg: format!("Failed to parse duration: {}", e),
+    })
+}
+
 /// Interceptors builtin sqlness
 fn builtin_interceptors() -> HashMap<String, InterceptorFactoryRef> {
     [
@@ -113,6 +130,22 @@ fn builtin_interceptors() -> HashMap<String, InterceptorFactoryRef> {
             sleep::PREFIX.to_string(),
             Arc::new(sleep::SleepInterceptorFactory {}) as _,
         ),
+        (
+            expect_error::PREFIX.to_string(),
+            Arc::new(ExpectErrorInterceptorFactory {}) as _,
+        ),
+        (
+            format::PREFIX.to_string(),
+            Arc::new(FormatInterceptorFactory {}) as _,
+        ),
+        (
+            retry::PREFIX.to_string(),
+            Arc::new(RetryInterceptorFactory {}) as _,
+        ),
+        (
+            timeout::PREFIX.to_string(),
+            Arc::new(TimeoutInterceptorFactory {}) as _,
+        ),
     ]
     .into_iter()
     .map(|(prefix, factory)| (prefix.to_string(), factory))
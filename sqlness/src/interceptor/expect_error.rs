@@ -0,0 +1,101 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use regex::Regex;
+
+use crate::case::QueryContext;
+use crate::error::Result;
+use crate::interceptor::{Interceptor, InterceptorFactory, InterceptorRef};
+use crate::SqlnessError;
+
+pub const PREFIX: &str = "EXPECT_ERROR";
+
+/// Expect the query to fail, and optionally its error message to match the
+/// given regular expression.
+///
+/// Grammar:
+/// ``` text
+/// -- SQLNESS EXPECT_ERROR [pattern]
+/// ```
+///
+/// The case fails if the query succeeds, or the error message doesn't match
+/// `pattern`. When the error is as expected, a fixed message is recorded
+/// instead of the error message, so the result doesn't depend on the exact
+/// error text.
+///
+/// This requires the [`Database`] to implement [`Database::query_result`].
+///
+/// # Example
+/// `.sql` file:
+/// ``` sql
+/// -- SQLNESS EXPECT_ERROR (?i)table .* not found
+/// SELECT * FROM not_exist;
+/// ```
+///
+/// `.result` file:
+/// ``` sql
+/// -- SQLNESS EXPECT_ERROR (?i)table .* not found
+/// SELECT * FROM not_exist;
+///
+/// Expected error occurred, matches: (?i)table .* not found
+/// ```
+///
+/// [`Database`]: crate::Database
+/// [`Database::query_result`]: crate::Database#method.query_result
+#[derive(Debug)]
+pub struct ExpectErrorInterceptor {
+    expected: ExpectedError,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpectedError {
+    /// Pattern the error message should match, any error is accepted if `None`.
+    pub pattern: Option<Regex>,
+}
+
+impl Interceptor for ExpectErrorInterceptor {
+    fn before_execute(&self, _: &mut Vec<String>, context: &mut QueryContext) {
+        context.expect_error = Some(self.expected.clone());
+    }
+}
+
+pub struct ExpectErrorInterceptorFactory;
+
+impl InterceptorFactory for ExpectErrorInterceptorFactory {
+    fn try_new(&self, ctx: &str) -> Result<InterceptorRef> {
+        let pattern = if ctx.is_empty() {
+            None
+        } else {
+            let pattern = Regex::new(ctx).map_err(|e| SqlnessError::InvalidContext {
+                prefix: PREFIX.to_string(),
+                msg: format!("Invalid pattern, err:{e}"),
+            })?;
+            Some(pattern)
+        };
+
+        Ok(Box::new(ExpectErrorInterceptor {
+            expected: ExpectedError { pattern },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construct_with_invalid_pattern() {
+        assert!(ExpectErrorInterceptorFactory.try_new("(").is_err());
+    }
+
+    #[test]
+    fn set_expected_error() {
+        let interceptor = ExpectErrorInterceptorFactory
+            .try_new("table .* not found")
+            .unwrap();
+        let mut context = QueryContext::default();
+        interceptor.before_execute(&mut vec![], &mut context);
+
+        let pattern = context.expect_error.unwrap().pattern.unwrap();
+        assert!(pattern.is_match("table foo not found"));
+    }
+}
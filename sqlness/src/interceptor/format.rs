@@ -0,0 +1,72 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use crate::case::QueryContext;
+use crate::error::Result;
+use crate::interceptor::{Interceptor, InterceptorFactory, InterceptorRef};
+use crate::{OutputFormat, SqlnessError};
+
+pub const PREFIX: &str = "FORMAT";
+
+/// Render the result set of this query in given style.
+///
+/// Grammar:
+/// ``` text
+/// -- SQLNESS FORMAT <table | csv | tsv | jsonl | sqllogictest>
+/// ```
+///
+/// It overrides the `format` of environment config, and only applies to
+/// databases that return structured results. See [`OutputFormat`] for details
+/// of each style.
+///
+/// # Example
+/// `.sql` file:
+/// ``` sql
+/// -- SQLNESS FORMAT csv
+/// SELECT 1 AS a, 'x' AS b;
+/// ```
+///
+/// `.result` file:
+/// ``` sql
+/// -- SQLNESS FORMAT csv
+/// SELECT 1 AS a, 'x' AS b;
+///
+/// a,b
+/// 1,x
+/// ```
+#[derive(Debug)]
+pub struct FormatInterceptor {
+    format: OutputFormat,
+}
+
+impl Interceptor for FormatInterceptor {
+    fn before_execute(&self, _: &mut Vec<String>, context: &mut QueryContext) {
+        context.format = self.format;
+    }
+}
+
+pub struct FormatInterceptorFactory;
+
+impl InterceptorFactory for FormatInterceptorFactory {
+    fn try_new(&self, ctx: &str) -> Result<InterceptorRef> {
+        let format = ctx.parse().map_err(|msg| SqlnessError::InvalidContext {
+            prefix: PREFIX.to_string(),
+            msg,
+        })?;
+        Ok(Box::new(FormatInterceptor { format }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_format() {
+        let interceptor = FormatInterceptorFactory.try_new("tsv").unwrap();
+        let mut context = QueryContext::default();
+        interceptor.before_execute(&mut vec![], &mut context);
+        assert_eq!(context.format, OutputFormat::Tsv);
+
+        assert!(FormatInterceptorFactory.try_new("").is_err());
+    }
+}
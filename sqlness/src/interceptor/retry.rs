@@ -0,0 +1,115 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use std::time::Duration;
+
+use crate::case::QueryContext;
+use crate::error::Result;
+use crate::interceptor::{parse_duration, Interceptor, InterceptorFactory, InterceptorRef};
+use crate::SqlnessError;
+
+pub const PREFIX: &str = "RETRY";
+
+/// Re-run the query until its result is the same as the expected one in
+/// `.result` file, useful for eventually-consistent queries.
+///
+/// Grammar:
+/// ``` text
+/// -- SQLNESS RETRY <max-attempts> <interval>
+/// ```
+///
+/// `max-attempts` counts the first execution. `interval` is the duration to wait
+/// between two attempts, in the same format as `SLEEP` interceptor. It's
+/// optional and default to `1s`.
+///
+/// The result is compared after all post-execution interceptors (like `SORT_RESULT`)
+/// are applied. Only the result of the last attempt is recorded. When there is no
+/// `.result` file, or previous queries in this case already differ from expected,
+/// the query is run only once.
+///
+/// # Example
+/// ``` sql
+/// -- SQLNESS RETRY 10 500ms
+/// SELECT count(*) FROM replicated_table;
+/// ```
+#[derive(Debug)]
+pub struct RetryInterceptor {
+    policy: RetryPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub interval: Duration,
+}
+
+impl Interceptor for RetryInterceptor {
+    fn before_execute(&self, _: &mut Vec<String>, context: &mut QueryContext) {
+        context.retry = Some(self.policy.clone());
+    }
+}
+
+pub struct RetryInterceptorFactory;
+
+impl InterceptorFactory for RetryInterceptorFactory {
+    fn try_new(&self, ctx: &str) -> Result<InterceptorRef> {
+        let mut args = ctx.split(' ').filter(|s| !s.is_empty());
+        let max_attempts = args
+            .next()
+            .ok_or_else(|| SqlnessError::InvalidContext {
+                prefix: PREFIX.to_string(),
+                msg: "Expect <max-attempts> [interval]".to_string(),
+            })?
+            .parse()
+            .map_err(|e| SqlnessError::InvalidContext {
+                prefix: PREFIX.to_string(),
+                msg: format!("Expect number, err:{e}"),
+            })?;
+        let interval = match args.next() {
+            Some(interval) => parse_duration(PREFIX, interval)?,
+            None => Duration::from_secs(1),
+        };
+
+        Ok(Box::new(RetryInterceptor {
+            policy: RetryPolicy {
+                max_attempts,
+                interval,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_policy() {
+        let cases = [
+            ("3", Some((3, Duration::from_secs(1)))),
+            ("5 200ms", Some((5, Duration::from_millis(200)))),
+            ("", None),
+            ("-1", None),
+            ("3 forever", None),
+        ];
+
+        for (input, expected) in cases {
+            let interceptor = RetryInterceptorFactory.try_new(input);
+            match expected {
+                Some((max_attempts, interval)) => {
+                    let mut context = QueryContext::default();
+                    interceptor
+                        .unwrap()
+                        .before_execute(&mut vec![], &mut context);
+                    assert_eq!(
+                        context.retry,
+                        Some(RetryPolicy {
+                            max_attempts,
+                            interval
+                        })
+                    );
+                }
+                None => assert!(interceptor.is_err()),
+            }
+        }
+    }
+}
@@ -1,12 +1,10 @@
 // Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
 
-use std::pin::Pin;
-use std::task::Context;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::error::Result;
-use crate::interceptor::{Interceptor, InterceptorFactory, InterceptorRef};
-use crate::SqlnessError;
+use crate::interceptor::{parse_duration, Interceptor, InterceptorFactory, InterceptorRef};
+use crate::timer;
 
 pub const PREFIX: &str = "SLEEP";
 
@@ -22,6 +20,7 @@ pub const PREFIX: &str = "SLEEP";
 /// - `1s` for 1 second
 /// - `1ms` for 1 millisecond
 /// - `1s500ms` for 1.5 seconds
+///
 /// etc. See detailed format in [duration_str](https://docs.rs/duration-str/0.11.2/duration_str/) crate
 ///
 /// Note that this implementation is not accurate and may be affected by the system load.
@@ -32,29 +31,6 @@ pub struct SleepInterceptor {
     duration: Duration,
 }
 
-struct Sleep {
-    now: Instant,
-    duration: Duration,
-}
-impl core::future::Future for Sleep {
-    type Output = ();
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> std::task::Poll<Self::Output> {
-        let elapsed = self.now.elapsed();
-        if elapsed < self.duration {
-            let waker = cx.waker().clone();
-            // detach the thread and let it wake the waker later
-            let remaining = self.duration.saturating_sub(elapsed);
-            std::thread::spawn(move || {
-                std::thread::sleep(remaining);
-                waker.wake();
-            });
-            std::task::Poll::Pending
-        } else {
-            std::task::Poll::Ready(())
-        }
-    }
-}
-
 #[async_trait::async_trait]
 impl Interceptor for SleepInterceptor {
     async fn before_execute_async(
@@ -62,12 +38,7 @@ impl Interceptor for SleepInterceptor {
         _execute_query: &mut Vec<String>,
         _context: &mut crate::case::QueryContext,
     ) {
-        // impl a cross-runtime sleep
-        Sleep {
-            now: Instant::now(),
-            duration: self.duration,
-        }
-        .await;
+        timer::sleep(self.duration).await;
     }
 }
 
@@ -75,16 +46,15 @@ pub struct SleepInterceptorFactory;
 
 impl InterceptorFactory for SleepInterceptorFactory {
     fn try_new(&self, ctx: &str) -> Result<InterceptorRef> {
-        let duration = duration_str::parse(ctx).map_err(|e| SqlnessError::InvalidContext {
-            prefix: PREFIX.to_string(),
-            msg: format!("Failed to parse duration: {}", e),
-        })?;
+        let duration = parse_duration(PREFIX, ctx)?;
         Ok(Box::new(SleepInterceptor { duration }))
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::time::Instant;
+
     use super::*;
 
     #[tokio::test]
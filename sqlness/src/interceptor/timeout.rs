@@ -0,0 +1,65 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use std::time::Duration;
+
+use crate::case::QueryContext;
+use crate::error::Result;
+use crate::interceptor::{parse_duration, Interceptor, InterceptorFactory, InterceptorRef};
+
+pub const PREFIX: &str = "TIMEOUT";
+
+/// Abort the query if it doesn't finish in given duration.
+///
+/// # Example
+/// ``` sql
+/// -- SQLNESS TIMEOUT <Duration>
+/// SELECT 1;
+/// ```
+///
+/// Duration format is the same as `SLEEP` interceptor, like `30s` or `1s500ms`.
+/// It overrides the global `query_timeout` in [`Config`].
+///
+/// A timed out query is recorded as `Query timed out after <Duration>` in the
+/// result, and the case is marked as failed.
+///
+/// [`Config`]: crate::Config
+#[derive(Debug)]
+pub struct TimeoutInterceptor {
+    timeout: Duration,
+}
+
+impl Interceptor for TimeoutInterceptor {
+    fn before_execute(&self, _: &mut Vec<String>, context: &mut QueryContext) {
+        context.timeout = Some(self.timeout);
+    }
+}
+
+pub struct TimeoutInterceptorFactory;
+
+impl InterceptorFactory for TimeoutInterceptorFactory {
+    fn try_new(&self, ctx: &str) -> Result<InterceptorRef> {
+        let timeout = parse_duration(PREFIX, ctx)?;
+        Ok(Box::new(TimeoutInterceptor { timeout }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn override_timeout() {
+        let interceptor = TimeoutInterceptorFactory {}.try_new("1s500ms").unwrap();
+        let mut context = QueryContext {
+            timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        interceptor.before_execute(&mut vec![], &mut context);
+        assert_eq!(context.timeout, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn invalid_duration() {
+        assert!(TimeoutInterceptorFactory {}.try_new("forever").is_err());
+    }
+}
@@ -10,8 +10,9 @@
 //! - Prepare the test case (of course!)
 //! - Implement [`EnvController`] and [`Database`]. They provide methods to start
 //!   the server, submit the query and clean up etc.
-//! - Format the result. Implement [`Display`] for your query result to make them
-//!   comparable.
+//! - Format the result. Either return a structured [`ResultSet`] from
+//!   [`Database::query_result`] and let sqlness render it, or implement
+//!   [`Display`] for your query result to make them comparable.
 //!
 //! And then all you need is to run the runner!
 //!
@@ -53,6 +54,36 @@
 //! different environments). All deeper layers are treated as the same. E.g.,
 //! both `sqlness/local/dml/basic.sql` and `sqlness/local/dml/another-dir/basic.sql`
 //! will be run under the `local` env in the same pass.
+//!
+//! ## Async runtime
+//!
+//! sqlness doesn't depend on any async runtime. Timers used by interceptors
+//! like `SLEEP` and `TIMEOUT` are driven by a background thread by default.
+//! Enable `tokio` or `async-std` feature to use timers of that runtime instead.
+//!
+//! ## Built-in databases
+//!
+//! [`database_impl`] provides ready-to-use [`Database`]s, each behind a cargo
+//! feature:
+//! - `mysql` and `postgres`: blocking clients, one connection per database.
+//! - `mysql-async` and `postgres-async`: non-blocking clients with a pool of
+//!   [`DatabaseConfig::pool_size`] connections, so concurrent cases (see
+//!   [`Config::parallelism`]) overlap. They require a tokio runtime.
+//! - `sqlite`: file-backed or in-memory SQLite compiled into the binary, no
+//!   database server needed.
+//! - `odbc`: databases with ODBC drivers, located by
+//!   [`DatabaseConfig::connection_string`]. It links to the system's ODBC
+//!   driver manager.
+//! - `flight-sql`: databases speaking Arrow Flight SQL. It requires a tokio
+//!   runtime.
+//! - `process`: databases only reachable through their own REPL binary,
+//!   which is spawned and talked to via stdin and stdout.
+//! - `http`: databases accepting queries over HTTP and responding in JSON,
+//!   with configurable request templates and response mapping.
+//!
+//! [`DatabaseConfig::pool_size`]: crate::DatabaseConfig#structfield.pool_size
+//! [`Config::parallelism`]: crate::Config#structfield.parallelism
+//! [`DatabaseConfig::connection_string`]: crate::DatabaseConfig#structfield.connection_string
 
 mod case;
 mod config;
@@ -60,12 +91,23 @@ mod database;
 pub mod database_impl;
 mod environment;
 mod error;
+mod format;
 pub mod interceptor;
+pub mod report;
+mod result_set;
 mod runner;
+pub mod splitter;
+mod timer;
 
 pub use case::QueryContext;
-pub use config::{Config, ConfigBuilder, DatabaseConfig, DatabaseConfigBuilder};
-pub use database::Database;
+pub use config::{
+    Config, ConfigBuilder, DatabaseConfig, DatabaseConfigBuilder, MissingResultPolicy,
+    NewResultOutput, ResultMode, TlsConfig, TlsMode, UnterminatedStatement,
+};
+pub use database::{Database, QueryResult};
 pub use environment::EnvController;
 pub use error::SqlnessError;
+pub use format::OutputFormat;
+pub use report::{CaseReport, CaseStatus, EnvReport, RunReport};
+pub use result_set::{Column, ResultSet, Value};
 pub use runner::Runner;
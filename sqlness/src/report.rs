@@ -0,0 +1,219 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Structured report of one run, and writers to export it.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{Result, SqlnessError};
+
+pub mod json;
+pub mod junit;
+pub mod tap;
+
+pub type ReportWriterRef = Arc<dyn ReportWriter + Send + Sync>;
+
+/// Export [`RunReport`] to somewhere else, like a file that CI can ingest.
+///
+/// Writers are configured via [`Config::report_writers`], and called after
+/// all environments finish.
+///
+/// [`Config::report_writers`]: crate::Config#structfield.report_writers
+pub trait ReportWriter {
+    fn write(&self, report: &RunReport) -> Result<()>;
+}
+
+/// Report of all environments, returned by [`Runner::run_with_report`].
+///
+/// Environments are listed in the order they are collected, and so are cases
+/// in each environment, regardless of the order they finish.
+///
+/// [`Runner::run_with_report`]: crate::Runner::run_with_report
+#[derive(Debug, Default)]
+pub struct RunReport {
+    pub environments: Vec<EnvReport>,
+}
+
+impl RunReport {
+    /// Return true when all environments pass.
+    pub fn is_success(&self) -> bool {
+        self.environments.iter().all(EnvReport::is_success)
+    }
+
+    /// Iterate over all cases of all environments.
+    pub fn cases(&self) -> impl Iterator<Item = (&EnvReport, &CaseReport)> {
+        self.environments
+            .iter()
+            .flat_map(|env| env.cases.iter().map(move |case| (env, case)))
+    }
+
+    /// Number of cases with given status.
+    pub fn count(&self, status: CaseStatus) -> usize {
+        self.cases()
+            .filter(|(_, case)| case.status == status)
+            .count()
+    }
+}
+
+/// Report of one environment.
+#[derive(Debug)]
+pub struct EnvReport {
+    /// Name of this environment, aka the first-level directory name.
+    pub name: String,
+    /// True when this environment is not run, either filtered out by
+    /// `env_filter` or stopped due to previous error under `fail_fast`.
+    pub skipped: bool,
+    pub elapsed: Duration,
+    pub cases: Vec<CaseReport>,
+    /// Error that stops this environment from running its cases.
+    pub error: Option<SqlnessError>,
+}
+
+impl EnvReport {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            skipped: false,
+            elapsed: Duration::ZERO,
+            cases: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn skipped(name: String) -> Self {
+        Self {
+            skipped: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// Return true when no error happens and all cases pass.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.failed_count() == 0
+    }
+
+    /// Number of cases that don't pass.
+    pub fn failed_count(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| !case.status.is_success())
+            .count()
+    }
+
+    /// Convert this report to the error of this environment, if any.
+    pub fn into_error(self) -> Option<SqlnessError> {
+        let count = self.failed_count();
+        match self.error {
+            Some(e) => Some(e),
+            None if count > 0 => Some(SqlnessError::RunFailed { count }),
+            None => None,
+        }
+    }
+}
+
+/// Report of one case.
+#[derive(Debug, Clone)]
+pub struct CaseReport {
+    /// Name of this case, the path relative to environment directory
+    /// without extension, e.g. `dml/basic`.
+    pub name: String,
+    /// Path to the case file.
+    pub path: PathBuf,
+    pub status: CaseStatus,
+    pub elapsed: Duration,
+    /// Diff between the expected and actual result when the case fails.
+    pub diff: Option<String>,
+    /// Error message when the case errors, prefixed by `path:line` or `path`,
+    /// or why the case fails other than diff, like timed out queries.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseStatus {
+    /// Actual result is the same as expected.
+    Passed,
+    /// Actual result differs from expected.
+    Failed,
+    /// Case can't be run to completion, like failed to parse or IO error.
+    Errored,
+    /// `.result` file doesn't exist and is created under [`MissingResultPolicy::Create`].
+    ///
+    /// [`MissingResultPolicy::Create`]: crate::MissingResultPolicy::Create
+    ResultCreated,
+    /// `.result` file doesn't exist under [`MissingResultPolicy::Fail`].
+    ///
+    /// [`MissingResultPolicy::Fail`]: crate::MissingResultPolicy::Fail
+    ResultMissing,
+    /// `.result` file doesn't exist under [`MissingResultPolicy::Warn`].
+    ///
+    /// [`MissingResultPolicy::Warn`]: crate::MissingResultPolicy::Warn
+    ResultMissingWarned,
+}
+
+impl CaseStatus {
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            CaseStatus::Passed | CaseStatus::ResultCreated | CaseStatus::ResultMissingWarned
+        )
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseStatus::Passed => "passed",
+            CaseStatus::Failed => "failed",
+            CaseStatus::Errored => "errored",
+            CaseStatus::ResultCreated => "result_created",
+            CaseStatus::ResultMissing => "result_missing",
+            CaseStatus::ResultMissingWarned => "result_missing_warned",
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    fn case(name: &str, status: CaseStatus) -> CaseReport {
+        CaseReport {
+            name: name.to_string(),
+            path: PathBuf::from(format!("cases/local/{name}.sql")),
+            status,
+            elapsed: Duration::from_millis(3),
+            diff: None,
+            error: None,
+        }
+    }
+
+    /// Report with one case of each outcome in environment `local`, an
+    /// environment `remote` failed to start, and a skipped environment.
+    pub(crate) fn sample_report() -> RunReport {
+        let mut local = EnvReport::new("local".to_string());
+        local.elapsed = Duration::from_millis(20);
+        local.cases = vec![
+            case("dml/basic", CaseStatus::Passed),
+            CaseReport {
+                diff: Some("  SELECT a FROM t;\n- 1\n+ 2\n".to_string()),
+                ..case("dml/bad", CaseStatus::Failed)
+            },
+            CaseReport {
+                error: Some("cases/local/dml/broken.sql:3: Parse failed".to_string()),
+                ..case("dml/broken", CaseStatus::Errored)
+            },
+            case("dml/missing", CaseStatus::ResultMissing),
+            case("dml/warned", CaseStatus::ResultMissingWarned),
+        ];
+
+        let mut remote = EnvReport::new("remote".to_string());
+        remote.elapsed = Duration::from_millis(7);
+        remote.error = Some(SqlnessError::InvalidEnvConfig {
+            file: PathBuf::from("cases/remote/config.toml"),
+            msg: "bad".to_string(),
+        });
+
+        RunReport {
+            environments: vec![local, remote, EnvReport::skipped("skipped".to_string())],
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::error::{Result, SqlnessError};
+use crate::report::{ReportWriter, RunReport};
+
+/// Write the report in [JSON Lines](https://jsonlines.org/) format.
+///
+/// Each case is one line like:
+/// ``` json
+/// {"env":"local","case":"dml/basic","path":"cases/local/dml/basic.sql","status":"failed","elapsed_ms":12,"diff":"...","error":null}
+/// ```
+///
+/// An environment that fails to run its cases is written as one line with
+/// `case` set to `null` and status `errored`.
+#[derive(Debug)]
+pub struct JsonLinesWriter {
+    path: PathBuf,
+}
+
+impl JsonLinesWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn render(report: &RunReport) -> String {
+        let mut output = String::new();
+        for env in &report.environments {
+            if let Some(e) = &env.error {
+                let line = json!({
+                    "env": env.name,
+                    "case": null,
+                    "path": null,
+                    "status": "errored",
+                    "elapsed_ms": env.elapsed.as_millis() as u64,
+                    "diff": null,
+                    "error": e.to_string(),
+                });
+                output.push_str(&line.to_string());
+                output.push('\n');
+            }
+            for case in &env.cases {
+                let line = json!({
+                    "env": env.name,
+                    "case": case.name,
+                    "path": case.path.to_string_lossy(),
+                    "status": case.status.as_str(),
+                    "elapsed_ms": case.elapsed.as_millis() as u64,
+                    "diff": case.diff,
+                    "error": case.error,
+                });
+                output.push_str(&line.to_string());
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+}
+
+impl ReportWriter for JsonLinesWriter {
+    fn write(&self, report: &RunReport) -> Result<()> {
+        std::fs::write(&self.path, Self::render(report)).map_err(|e| SqlnessError::WritePath {
+            source: e,
+            path: self.path.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::report::test::sample_report;
+
+    #[test]
+    fn render_one_line_per_case() {
+        let lines = JsonLinesWriter::render(&sample_report())
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect::<Vec<Value>>();
+
+        // The skipped environment has no line.
+        let expected = vec![
+            json!({
+                "env": "local", "case": "dml/basic", "path": "cases/local/dml/basic.sql",
+                "status": "passed", "elapsed_ms": 3, "diff": null, "error": null,
+            }),
+            json!({
+                "env": "local", "case": "dml/bad", "path": "cases/local/dml/bad.sql",
+                "status": "failed", "elapsed_ms": 3, "diff": "  SELECT a FROM t;\n- 1\n+ 2\n",
+                "error": null,
+            }),
+            json!({
+                "env": "local", "case": "dml/broken", "path": "cases/local/dml/broken.sql",
+                "status": "errored", "elapsed_ms": 3, "diff": null,
+                "error": "cases/local/dml/broken.sql:3: Parse failed",
+            }),
+            json!({
+                "env": "local", "case": "dml/missing", "path": "cases/local/dml/missing.sql",
+                "status": "result_missing", "elapsed_ms": 3, "diff": null, "error": null,
+            }),
+            json!({
+                "env": "local", "case": "dml/warned", "path": "cases/local/dml/warned.sql",
+                "status": "result_missing_warned", "elapsed_ms": 3, "diff": null, "error": null,
+            }),
+            json!({
+                "env": "remote", "case": null, "path": null, "status": "errored",
+                "elapsed_ms": 7, "diff": null,
+                "error": "Invalid config in cases/remote/config.toml, msg:bad.",
+            }),
+        ];
+        assert_eq!(lines, expected);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::error::{Result, SqlnessError};
+use crate::report::{CaseStatus, EnvReport, ReportWriter, RunReport};
+
+/// Write the report in [JUnit XML](https://github.com/testmoapp/junitxml) format.
+///
+/// Each environment is a `<testsuite>`, and each case is a `<testcase>` in it.
+/// Diff of a failed case is put in the `<failure>` element.
+///
+/// An environment that fails to run its cases, or is skipped, gets one more
+/// `<testcase>` named `(environment)`, with the error in its `<error>`
+/// element or a `<skipped>` element.
+#[derive(Debug)]
+pub struct JunitWriter {
+    path: PathBuf,
+}
+
+/// Name of the `<testcase>` standing for the environment itself.
+const ENV_CASE: &str = "(environment)";
+
+impl JunitWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn render(report: &RunReport) -> String {
+        let mut xml = String::new();
+        let suites = report
+            .environments
+            .iter()
+            .map(Counts::of)
+            .collect::<Vec<_>>();
+        let total = suites
+            .iter()
+            .fold(Counts::default(), |total, suite| Counts {
+                tests: total.tests + suite.tests,
+                failures: total.failures + suite.failures,
+                errors: total.errors + suite.errors,
+                skipped: total.skipped + suite.skipped,
+            });
+        let time: f64 = report
+            .environments
+            .iter()
+            .map(|env| env.elapsed.as_secs_f64())
+            .sum();
+
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"sqlness\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{time:.3}\">",
+            total.tests, total.failures, total.errors, total.skipped
+        );
+        for (env, counts) in report.environments.iter().zip(suites) {
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+                escape(&env.name),
+                counts.tests,
+                counts.failures,
+                counts.errors,
+                counts.skipped,
+                env.elapsed.as_secs_f64()
+            );
+            if env.skipped {
+                let _ = writeln!(
+                    xml,
+                    "    <testcase name=\"{ENV_CASE}\" classname=\"{}\" time=\"0.000\">\n      <skipped message=\"Environment skipped\"/>\n    </testcase>",
+                    escape(&env.name)
+                );
+            }
+            if let Some(e) = &env.error {
+                let _ = writeln!(
+                    xml,
+                    "    <testcase name=\"{ENV_CASE}\" classname=\"{}\" time=\"{:.3}\">\n      <error message=\"{}\"></error>\n    </testcase>",
+                    escape(&env.name),
+                    env.elapsed.as_secs_f64(),
+                    escape(&e.to_string())
+                );
+            }
+            for case in &env.cases {
+                let _ = write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" time=\"{:.3}\"",
+                    escape(&case.name),
+                    escape(&env.name),
+                    escape(&case.path.to_string_lossy()),
+                    case.elapsed.as_secs_f64()
+                );
+                match case.status {
+                    CaseStatus::Passed | CaseStatus::ResultCreated => xml.push_str("/>\n"),
+                    CaseStatus::ResultMissing => {
+                        xml.push_str(
+                            ">\n      <failure message=\"Result file missing\"></failure>\n    </testcase>\n",
+                        );
+                    }
+                    CaseStatus::ResultMissingWarned => {
+                        xml.push_str(
+                            ">\n      <skipped message=\"Result file missing\"/>\n    </testcase>\n",
+                        );
+                    }
+                    CaseStatus::Failed => {
+                        let _ = writeln!(
+                            xml,
+                            ">\n      <failure message=\"Result unexpected\">{}</failure>\n    </testcase>",
+                            escape(case.diff.as_deref().or(case.error.as_deref()).unwrap_or_default())
+                        );
+                    }
+                    CaseStatus::Errored => {
+                        let error = case.error.as_deref().unwrap_or_default();
+                        let _ = writeln!(
+                            xml,
+                            ">\n      <error message=\"{}\"></error>\n    </testcase>",
+                            escape(error)
+                        );
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+
+        xml
+    }
+}
+
+impl ReportWriter for JunitWriter {
+    fn write(&self, report: &RunReport) -> Result<()> {
+        std::fs::write(&self.path, Self::render(report)).map_err(|e| SqlnessError::WritePath {
+            source: e,
+            path: self.path.clone(),
+        })
+    }
+}
+
+/// Numbers of test cases in a `<testsuite>`, the ones of `<testsuites>` are
+/// the sums of all suites.
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn of(env: &EnvReport) -> Self {
+        let count =
+            |f: fn(CaseStatus) -> bool| env.cases.iter().filter(|case| f(case.status)).count();
+        let env_errors = usize::from(env.error.is_some());
+        let env_skipped = usize::from(env.skipped);
+        Self {
+            tests: env.cases.len() + env_errors + env_skipped,
+            failures: count(|status| {
+                matches!(status, CaseStatus::Failed | CaseStatus::ResultMissing)
+            }),
+            errors: count(|status| status == CaseStatus::Errored) + env_errors,
+            skipped: count(|status| status == CaseStatus::ResultMissingWarned) + env_skipped,
+        }
+    }
+}
+
+/// Escape special characters in XML attribute or text.
+fn escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0, except tab and newlines.
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::report::test::sample_report;
+    use crate::report::CaseReport;
+
+    #[test]
+    fn render_failed_case() {
+        let mut env = EnvReport::new("local".to_string());
+        env.cases = vec![
+            CaseReport {
+                name: "dml/basic".to_string(),
+                path: PathBuf::from("cases/local/dml/basic.sql"),
+                status: CaseStatus::Passed,
+                elapsed: Duration::from_millis(12),
+                diff: None,
+                error: None,
+            },
+            CaseReport {
+                name: "dml/bad".to_string(),
+                path: PathBuf::from("cases/local/dml/bad.sql"),
+                status: CaseStatus::Failed,
+                elapsed: Duration::from_millis(1),
+                diff: Some("- a < b\n+ a > b\n".to_string()),
+                error: None,
+            },
+        ];
+        let report = RunReport {
+            environments: vec![env],
+        };
+
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="sqlness" tests="2" failures="1" errors="0" skipped="0" time="0.000">
+  <testsuite name="local" tests="2" failures="1" errors="0" skipped="0" time="0.000">
+    <testcase name="dml/basic" classname="local" file="cases/local/dml/basic.sql" time="0.012"/>
+    <testcase name="dml/bad" classname="local" file="cases/local/dml/bad.sql" time="0.001">
+      <failure message="Result unexpected">- a &lt; b
++ a &gt; b
+</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        assert_eq!(JunitWriter::render(&report), expected);
+    }
+
+    #[test]
+    fn render_totals_of_suites() {
+        let xml = JunitWriter::render(&sample_report());
+        let header = xml.lines().nth(1).unwrap();
+        assert_eq!(
+            header,
+            r#"<testsuites name="sqlness" tests="7" failures="2" errors="2" skipped="2" time="0.027">"#
+        );
+    }
+
+    #[test]
+    fn render_env_error_as_testcase() {
+        let xml = JunitWriter::render(&sample_report());
+        let expected = r#"  <testsuite name="remote" tests="1" failures="0" errors="1" skipped="0" time="0.007">
+    <testcase name="(environment)" classname="remote" time="0.007">
+      <error message="Invalid config in cases/remote/config.toml, msg:bad."></error>
+    </testcase>
+  </testsuite>
+"#;
+        assert!(xml.contains(expected), "{xml}");
+    }
+
+    #[test]
+    fn render_skipped_testcases() {
+        let xml = JunitWriter::render(&sample_report());
+        assert!(xml.contains(
+            r#"<testsuite name="local" tests="5" failures="2" errors="1" skipped="1" time="0.020">"#
+        ));
+        let expected = r#"  <testsuite name="skipped" tests="1" failures="0" errors="0" skipped="1" time="0.000">
+    <testcase name="(environment)" classname="skipped" time="0.000">
+      <skipped message="Environment skipped"/>
+    </testcase>
+  </testsuite>
+"#;
+        assert!(xml.contains(expected), "{xml}");
+    }
+}
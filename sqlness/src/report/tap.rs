@@ -0,0 +1,174 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::error::{Result, SqlnessError};
+use crate::report::{CaseStatus, ReportWriter, RunReport};
+
+/// Write the report in [TAP version 13](https://testanything.org/tap-version-13-specification.html) format.
+///
+/// Each case is one test point named `{env}:{case}`, diff or error of a
+/// failed case is attached as a YAML block.
+#[derive(Debug)]
+pub struct TapWriter {
+    path: PathBuf,
+}
+
+impl TapWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn render(report: &RunReport) -> String {
+        let mut output = String::from("TAP version 13\n");
+        let total = report.cases().count()
+            + report
+                .environments
+                .iter()
+                .filter(|env| env.error.is_some() || env.skipped)
+                .count();
+        let _ = writeln!(output, "1..{total}");
+
+        let mut index = 0;
+        for env in &report.environments {
+            if env.skipped {
+                index += 1;
+                let _ = writeln!(output, "ok {index} - {} # SKIP", env.name);
+            }
+            if let Some(e) = &env.error {
+                index += 1;
+                let _ = writeln!(output, "not ok {index} - {}", env.name);
+                write_yaml_block(&mut output, "error", &e.to_string());
+            }
+            for case in &env.cases {
+                index += 1;
+                let name = format!("{}:{}", env.name, case.name);
+                match case.status {
+                    CaseStatus::Passed | CaseStatus::ResultCreated => {
+                        let _ = writeln!(output, "ok {index} - {name}");
+                    }
+                    CaseStatus::ResultMissingWarned => {
+                        let _ = writeln!(output, "ok {index} - {name} # SKIP result file missing");
+                    }
+                    CaseStatus::ResultMissing => {
+                        let _ = writeln!(output, "not ok {index} - {name}");
+                        write_yaml_block(&mut output, "error", "Result file missing");
+                    }
+                    CaseStatus::Failed => {
+                        let _ = writeln!(output, "not ok {index} - {name}");
+                        match (&case.diff, &case.error) {
+                            (Some(diff), _) => write_yaml_block(&mut output, "diff", diff),
+                            (None, error) => write_yaml_block(
+                                &mut output,
+                                "error",
+                                error.as_deref().unwrap_or_default(),
+                            ),
+                        }
+                    }
+                    CaseStatus::Errored => {
+                        let _ = writeln!(output, "not ok {index} - {name}");
+                        write_yaml_block(
+                            &mut output,
+                            "error",
+                            case.error.as_deref().unwrap_or_default(),
+                        );
+                    }
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// Write `key` with a multi-line `value` as a YAML block.
+///
+/// The indentation is explicit, as lines of a diff may start with spaces.
+fn write_yaml_block(output: &mut String, key: &str, value: &str) {
+    let _ = writeln!(output, "  ---\n  {key}: |2");
+    for line in value.lines() {
+        let _ = writeln!(output, "    {line}");
+    }
+    output.push_str("  ...\n");
+}
+
+impl ReportWriter for TapWriter {
+    fn write(&self, report: &RunReport) -> Result<()> {
+        std::fs::write(&self.path, Self::render(report)).map_err(|e| SqlnessError::WritePath {
+            source: e,
+            path: self.path.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::report::test::sample_report;
+
+    #[test]
+    fn render_test_points() {
+        let expected = "TAP version 13
+1..7
+ok 1 - local:dml/basic
+not ok 2 - local:dml/bad
+  ---
+  diff: |2
+      SELECT a FROM t;
+    - 1
+    + 2
+  ...
+not ok 3 - local:dml/broken
+  ---
+  error: |2
+    cases/local/dml/broken.sql:3: Parse failed
+  ...
+not ok 4 - local:dml/missing
+  ---
+  error: |2
+    Result file missing
+  ...
+ok 5 - local:dml/warned # SKIP result file missing
+not ok 6 - remote
+  ---
+  error: |2
+    Invalid config in cases/remote/config.toml, msg:bad.
+  ...
+ok 7 - skipped # SKIP
+";
+        assert_eq!(TapWriter::render(&sample_report()), expected);
+    }
+
+    #[test]
+    fn parse_yaml_blocks() {
+        let output = TapWriter::render(&sample_report());
+        let blocks = output
+            .split("  ---\n")
+            .skip(1)
+            .map(|block| {
+                let yaml = block.split("  ...\n").next().unwrap();
+                // YAML blocks are indented by 2 spaces in TAP.
+                let yaml = yaml
+                    .lines()
+                    .map(|line| format!("{}\n", line.strip_prefix("  ").unwrap_or(line)))
+                    .collect::<String>();
+                serde_yaml::from_str(&yaml).unwrap()
+            })
+            .collect::<Vec<HashMap<String, String>>>();
+
+        let expected = [
+            ("diff", "  SELECT a FROM t;\n- 1\n+ 2\n"),
+            ("error", "cases/local/dml/broken.sql:3: Parse failed\n"),
+            ("error", "Result file missing\n"),
+            (
+                "error",
+                "Invalid config in cases/remote/config.toml, msg:bad.\n",
+            ),
+        ]
+        .map(|(key, value)| HashMap::from([(key.to_string(), value.to_string())]));
+        assert_eq!(blocks, expected);
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Structured query results, rendered by sqlness so results of different
+//! databases are comparable.
+
+use std::fmt::{Display, Formatter, Write};
+
+/// Rows returned by a query, along with their columns.
+///
+/// The canonical rendering of a result set is an ASCII table:
+/// ``` text
+/// +----+-------+
+/// | id | name  |
+/// +----+-------+
+/// | 1  | hello |
+/// | 2  | NULL  |
+/// +----+-------+
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<Column>,
+    /// Each row has exactly one value per column.
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push_row(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    /// Type name reported by the database, like `INT` or `varchar`.
+    pub data_type: String,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, data_type: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            data_type: data_type.into(),
+        }
+    }
+}
+
+/// A typed value in a [`ResultSet`].
+///
+/// Temporal and decimal values are kept as text, formatted by the backend
+/// in their canonical form, e.g. `2024-01-02 03:04:05.123456`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Decimal(String),
+    String(String),
+    Bytes(Vec<u8>),
+    Date(String),
+    Time(String),
+    Timestamp(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Int(v) => write!(f, "{v}"),
+            Value::UInt(v) => write!(f, "{v}"),
+            // Shortest representation that round-trips, e.g. `0.1` and `1`.
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Bytes(v) => {
+                f.write_str("0x")?;
+                v.iter().try_for_each(|b| write!(f, "{b:02x}"))
+            }
+            Value::Decimal(v)
+            | Value::String(v)
+            | Value::Date(v)
+            | Value::Time(v)
+            | Value::Timestamp(v) => f.write_str(v),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::UInt(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl Display for ResultSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let header = self
+            .columns
+            .iter()
+            .map(|column| escape_cell(&column.name))
+            .collect::<Vec<_>>();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| escape_cell(&value.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = header
+            .iter()
+            .map(|name| name.chars().count())
+            .collect::<Vec<_>>();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut separator = String::from("+");
+        for width in &widths {
+            separator.push_str(&"-".repeat(width + 2));
+            separator.push('+');
+        }
+        let write_row = |f: &mut Formatter<'_>, cells: &[String]| {
+            f.write_char('|')?;
+            for (cell, width) in cells.iter().zip(&widths) {
+                write!(f, " {cell:<width$} |")?;
+            }
+            f.write_char('\n')
+        };
+
+        writeln!(f, "{separator}")?;
+        write_row(f, &header)?;
+        writeln!(f, "{separator}")?;
+        for row in &rows {
+            write_row(f, row)?;
+        }
+        write!(f, "{separator}")
+    }
+}
+
+/// Escape line breaks so each row takes exactly one line.
+fn escape_cell(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_values() {
+        let cases = [
+            (Value::Null, "NULL"),
+            (Value::Bool(true), "true"),
+            (Value::Int(-1), "-1"),
+            (Value::UInt(u64::MAX), "18446744073709551615"),
+            (Value::Float(1.0), "1"),
+            (Value::Float(0.1), "0.1"),
+            (Value::Decimal("1.50".to_string()), "1.50"),
+            (Value::Bytes(vec![0, 0xab]), "0x00ab"),
+            (Value::from(None::<i64>), "NULL"),
+            (Value::from("text"), "text"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn render_table() {
+        let mut result =
+            ResultSet::new(vec![Column::new("id", "INT"), Column::new("name", "TEXT")]);
+        result.push_row(vec![Value::Int(1), Value::from("hello")]);
+        result.push_row(vec![Value::Int(20), Value::from("a\nb")]);
+        result.push_row(vec![Value::Int(3), Value::Null]);
+
+        let expected = "\
++----+-------+
+| id | name  |
++----+-------+
+| 1  | hello |
+| 20 | a\\nb  |
+| 3  | NULL  |
++----+-------+";
+        assert_eq!(result.to_string(), expected);
+    }
+}
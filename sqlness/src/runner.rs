@@ -1,11 +1,15 @@
 // Copyright 2022 CeresDB Project Authors. Licensed under Apache-2.0.
 
-use std::fs::{read_dir, OpenOptions};
-use std::io::{Cursor, Read, Seek, Write};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{create_dir_all, read_dir, File, OpenOptions};
+use std::io::{Cursor, ErrorKind, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
+use futures::stream::{self, StreamExt};
 use prettydiff::basic::{DiffOp, SliceChangeset};
 use prettydiff::diff_lines;
 use regex::Regex;
@@ -13,7 +17,34 @@ use walkdir::WalkDir;
 
 use crate::case::TestCase;
 use crate::error::{Result, SqlnessError};
-use crate::{config::Config, environment::EnvController};
+use crate::report::{CaseReport, CaseStatus, EnvReport, RunReport};
+use crate::splitter::SplitterRef;
+use crate::{
+    config::{Config, EnvConfig, MissingResultPolicy, NewResultOutput, ResultMode},
+    environment::EnvController,
+};
+
+/// Extension appended to the result extension for [`NewResultOutput::Sibling`].
+const NEW_RESULT_EXTENSION: &str = "new";
+
+/// Outcome of one case that runs to completion.
+struct CaseOutcome {
+    status: CaseStatus,
+    /// Diff between the expected and actual result.
+    diff: Option<String>,
+    /// Why this case fails, other than diff.
+    error: Option<String>,
+}
+
+impl CaseOutcome {
+    fn new(status: CaseStatus) -> Self {
+        Self {
+            status,
+            diff: None,
+            error: None,
+        }
+    }
+}
 
 /// The entrypoint of this crate.
 ///
@@ -43,43 +74,120 @@ impl<E: EnvController> Runner<E> {
         }
     }
 
+    /// Run all cases, return the error of failed environments.
+    ///
+    /// Use [`run_with_report`] to get detailed result of each case.
+    ///
+    /// [`run_with_report`]: crate::Runner#method.run_with_report
     pub async fn run(&self) -> Result<()> {
+        let report = self.run_with_report().await?;
+        let mut errors = report
+            .environments
+            .into_iter()
+            .filter_map(|env| {
+                let name = env.name.clone();
+                env.into_error().map(|e| (name, e))
+            })
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else if self.config.fail_fast {
+            // only return first error
+            Err(errors.swap_remove(0).1)
+        } else {
+            Err(SqlnessError::EnvRunFailed { errors })
+        }
+    }
+
+    /// Run all cases and return the report of every environment and case.
+    ///
+    /// Failed cases won't make this method return an error, check
+    /// [`RunReport::is_success`] instead.
+    pub async fn run_with_report(&self) -> Result<RunReport> {
         let environments = self.collect_env()?;
-        let mut errors = Vec::new();
         let filter = Regex::new(&self.config.env_filter)?;
-        for env in environments {
-            if !filter.is_match(&env) {
-                println!("Environment({env}) is skipped!");
-                continue;
-            }
-            let env_config = self.read_env_config(&env);
-            let config_path = env_config.as_path();
-            let config_path = if config_path.exists() {
-                Some(config_path)
-            } else {
-                None
-            };
-            let db = self.env_controller.start(&env, config_path).await;
-            let run_result = self.run_env(&env, &db).await;
-            self.env_controller.stop(&env, db).await;
+        let filter = &filter;
 
-            if let Err(e) = run_result {
-                println!("Environment {env} run failed, error:{e:?}.");
-
-                if self.config.fail_fast {
-                    return Err(e);
+        // Set when one environment fails under `fail_fast`, environments not
+        // started yet will be skipped.
+        let stopped = AtomicBool::new(false);
+        let stopped = &stopped;
+        let mut runs = stream::iter(environments.into_iter().enumerate())
+            .map(|(index, env)| async move {
+                if !filter.is_match(&env) {
+                    println!("Environment({env}) is skipped!");
+                    return (index, EnvReport::skipped(env));
+                }
+                if stopped.load(Ordering::Relaxed) {
+                    println!("Environment {env} is skipped due to previous error.");
+                    return (index, EnvReport::skipped(env));
+                }
+                let env_report = self.start_and_run_env(env).await;
+                if !env_report.is_success() && self.config.fail_fast {
+                    stopped.store(true, Ordering::Relaxed);
                 }
+                (index, env_report)
+            })
+            .buffer_unordered(self.config.env_parallelism.max(1));
 
-                errors.push(e);
+        let mut environments = Vec::new();
+        while let Some((index, env_report)) = runs.next().await {
+            if let Some(e) = &env_report.error {
+                println!("Environment {} run failed, error:{e:?}.", env_report.name);
+            } else if !env_report.is_success() {
+                let e = SqlnessError::RunFailed {
+                    count: env_report.failed_count(),
+                };
+                println!("Environment {} run failed, error:{e:?}.", env_report.name);
             }
+            environments.push((index, env_report));
+        }
+
+        // Environments may finish in any order, report them in the order they are collected.
+        environments.sort_by_key(|(index, _)| *index);
+        let environments = environments
+            .into_iter()
+            .map(|(_, env_report)| env_report)
+            .collect();
+        let report = RunReport { environments };
+
+        for writer in &self.config.report_writers {
+            writer.write(&report)?;
         }
 
-        // only return first error
-        if let Some(e) = errors.pop() {
-            return Err(e);
+        Ok(report)
+    }
+
+    /// Start the environment via [`EnvController`], run all its cases and stop it.
+    async fn start_and_run_env(&self, env: String) -> EnvReport {
+        let env_config = self.read_env_config(&env);
+        let config_path = env_config.as_path();
+        let config_path = if config_path.exists() {
+            Some(config_path)
+        } else {
+            None
+        };
+        let mut env_report = EnvReport::new(env);
+        let env_config = match config_path.map(EnvConfig::from_file).transpose() {
+            Ok(env_config) => env_config.unwrap_or_default(),
+            Err(e) => {
+                env_report.error = Some(e);
+                return env_report;
+            }
+        };
+        let db = self
+            .env_controller
+            .start(&env_report.name, config_path)
+            .await;
+        let start = Instant::now();
+        if let Err(e) = self.run_env(&db, &env_config, &mut env_report).await {
+            env_report.error = Some(e);
         }
+        env_report.elapsed = start.elapsed();
+        self.env_controller.stop(&env_report.name, db).await;
 
-        Ok(())
+        env_report
     }
 
     fn read_env_config(&self, env: &str) -> PathBuf {
@@ -101,31 +209,82 @@ impl<E: EnvController> Runner<E> {
                 result.push(file_name);
             }
         }
+        // `read_dir` yields in an os-dependent order.
+        result.sort();
 
         Ok(result)
     }
 
-    async fn run_env(&self, env: &str, db: &E::DB) -> Result<()> {
-        let case_paths = self.collect_case_paths(env).await?;
-        let mut failed_cases = vec![];
-        let mut errors = vec![];
+    /// Run all cases of one environment, and record them to the report.
+    async fn run_env(
+        &self,
+        db: &E::DB,
+        env_config: &EnvConfig,
+        env_report: &mut EnvReport,
+    ) -> Result<()> {
+        let env = env_report.name.as_str();
+        let splitters = self.case_splitters(env_config)?;
+        let splitters = &splitters;
+        let case_paths = self.collect_case_paths(env, splitters).await?;
+        let root = self.env_root(env);
+        let mut case_reports = vec![];
         let start = Instant::now();
-        for path in case_paths {
-            let is_success = self.run_single_case(db, &path).await;
-            let case_name = path.as_os_str().to_str().unwrap().to_owned();
-            match is_success {
-                Ok(false) => failed_cases.push(case_name),
-                Ok(true) => {}
+
+        let mut cases = stream::iter(case_paths.into_iter().enumerate())
+            .map(|(index, path)| async move {
+                let mut output = String::new();
+                let timer = Instant::now();
+                let result = self
+                    .run_single_case(db, env_config, splitters, &path, &mut output)
+                    .await;
+                (index, path, result, timer.elapsed(), output)
+            })
+            .buffer_unordered(self.config.parallelism.max(1));
+        while let Some((index, path, result, elapsed, output)) = cases.next().await {
+            // Output of one case is printed at once, so logs from concurrently
+            // running cases won't interleave.
+            print!("{output}");
+            let name = path
+                .strip_prefix(&root)
+                .unwrap_or(&path)
+                .with_extension("")
+                .to_string_lossy()
+                .into_owned();
+            let mut case_report = CaseReport {
+                name,
+                path: path.clone(),
+                status: CaseStatus::Passed,
+                elapsed,
+                diff: None,
+                error: None,
+            };
+            let mut should_stop = false;
+            match result {
+                Ok(outcome) => {
+                    case_report.status = outcome.status;
+                    case_report.diff = outcome.diff;
+                    case_report.error = outcome.error;
+                }
                 Err(e) => {
+                    // Reported as `path:line: message` or `path: message`,
+                    // which editors can jump to.
+                    let message = match e {
+                        SqlnessError::ParseCase { .. } => e.to_string(),
+                        e => format!("{}: {e}", path.display()),
+                    };
                     if self.config.fail_fast {
-                        println!("Case {case_name} failed with error {e:?}");
+                        println!("{message}");
                         println!("Stopping environment {env} due to previous error.");
-                        break;
-                    } else {
-                        errors.push((case_name, e))
+                        should_stop = true;
                     }
+                    case_report.status = CaseStatus::Errored;
+                    case_report.error = Some(message);
                 }
             }
+            case_reports.push((index, case_report));
+            if should_stop {
+                break;
+            }
         }
 
         println!(
@@ -134,74 +293,257 @@ impl<E: EnvController> Runner<E> {
             start.elapsed().as_millis()
         );
 
-        if !failed_cases.is_empty() {
-            println!("Failed cases:");
-            println!("{failed_cases:#?}");
+        // Cases may finish in any order, report them in the order they are collected.
+        case_reports.sort_by_key(|(index, _)| *index);
+        env_report.cases = case_reports
+            .into_iter()
+            .map(|(_, case_report)| case_report)
+            .collect();
+
+        for (title, status) in [
+            ("Failed cases:", CaseStatus::Failed),
+            ("Missing result cases:", CaseStatus::ResultMissing),
+            (
+                "Missing result cases (ignored):",
+                CaseStatus::ResultMissingWarned,
+            ),
+            ("Created result cases:", CaseStatus::ResultCreated),
+        ] {
+            let cases = env_report
+                .cases
+                .iter()
+                .filter(|case| case.status == status)
+                .map(|case| case.path.with_extension("").display().to_string())
+                .collect::<Vec<_>>();
+            if !cases.is_empty() {
+                println!("{title}");
+                println!("{cases:#?}");
+            }
         }
 
+        let errors = env_report
+            .cases
+            .iter()
+            .filter(|case| case.status == CaseStatus::Errored)
+            .filter_map(|case| case.error.as_deref())
+            .collect::<Vec<_>>();
         if !errors.is_empty() {
             println!("Error cases:");
-            println!("{errors:#?}");
+            for error in errors {
+                println!("{error}");
+            }
         }
 
-        let error_count = failed_cases.len() + errors.len();
-        if error_count == 0 {
-            Ok(())
-        } else {
-            Err(SqlnessError::RunFailed { count: error_count })
-        }
+        Ok(())
+    }
+
+    /// Splitters of one environment by case file extension.
+    fn case_splitters(&self, env_config: &EnvConfig) -> Result<HashMap<String, SplitterRef>> {
+        self.config
+            .splitter_names(&env_config.splitters)
+            .into_iter()
+            .map(|(extension, name)| {
+                let splitter = self
+                    .config
+                    .splitter_registry
+                    .get(&name)
+                    .ok_or(SqlnessError::UnknownSplitter { name })?;
+                Ok((extension, splitter))
+            })
+            .collect()
     }
 
-    /// Return true when this case pass, otherwise false.
-    async fn run_single_case(&self, db: &E::DB, path: &Path) -> Result<bool> {
-        let case_path = path.with_extension(&self.config.test_case_extension);
-        let mut case = TestCase::from_file(&case_path, &self.config)?;
+    /// Run one case and return its outcome.
+    ///
+    /// Logs of this case are written to `output` instead of stdout.
+    async fn run_single_case(
+        &self,
+        db: &E::DB,
+        env_config: &EnvConfig,
+        splitters: &HashMap<String, SplitterRef>,
+        case_path: &Path,
+        output: &mut String,
+    ) -> Result<CaseOutcome> {
+        let path = case_path.with_extension("");
+        // Case paths are collected by extensions of splitters.
+        let splitter = case_path
+            .extension()
+            .and_then(|extension| splitters.get(extension.to_str()?))
+            .expect("splitter of case exists");
+        let mut case = TestCase::from_file(case_path, &self.config, env_config, splitter)?;
         let result_path = path.with_extension(&self.config.result_extension);
-        let mut result_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .read(true)
-            .truncate(false)
-            .open(&result_path)?;
+
+        let missing_result_policy = if result_path.exists() {
+            None
+        } else if self.config.result_mode == ResultMode::Check {
+            // result files are never created under check mode
+            match self.config.missing_result_policy {
+                MissingResultPolicy::Create => Some(MissingResultPolicy::Fail),
+                policy => Some(policy),
+            }
+        } else {
+            Some(self.config.missing_result_policy)
+        };
+        let writable = self.config.result_mode != ResultMode::Check
+            && matches!(
+                missing_result_policy,
+                None | Some(MissingResultPolicy::Create)
+            );
 
         // Read old result out for compare later
         let mut old_result = String::new();
-        result_file.read_to_string(&mut old_result)?;
+        let mut result_file = if writable {
+            let mut result_file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .read(true)
+                .truncate(false)
+                .open(&result_path)?;
+            result_file.read_to_string(&mut old_result)?;
+            Some(result_file)
+        } else {
+            if missing_result_policy.is_none() {
+                File::open(&result_path)?.read_to_string(&mut old_result)?;
+            }
+            None
+        };
 
         // Execute testcase
         let mut new_result = Cursor::new(Vec::new());
         let timer = Instant::now();
-        case.execute(db, &mut new_result).await?;
+        let expected = missing_result_policy
+            .is_none()
+            .then_some(old_result.as_str());
+        let failures = case.execute(db, expected, &mut new_result).await?;
         let elapsed = timer.elapsed();
 
-        // Truncate and write new result back
-        result_file.set_len(0)?;
-        result_file.rewind()?;
-        result_file.write_all(new_result.get_ref())?;
-
         // Compare old and new result
         let new_result = String::from_utf8(new_result.into_inner()).expect("not utf8 string");
-        if let Some(diff) = self.compare(&old_result, &new_result) {
-            println!("Result unexpected, path:{case_path:?}");
-            println!("{diff}");
-            return Ok(false);
+        let diff = match missing_result_policy {
+            None => self.compare(&old_result, &new_result),
+            Some(_) => None,
+        };
+
+        if let Some(result_file) = &mut result_file {
+            // Truncate and write new result back
+            result_file.set_len(0)?;
+            result_file.rewind()?;
+            result_file.write_all(new_result.as_bytes())?;
+        } else if self.config.result_mode == ResultMode::Check {
+            let changed = missing_result_policy.is_some() || diff.is_some();
+            self.write_new_result(&result_path, new_result.as_bytes(), changed)?;
         }
 
-        println!(
+        // Some queries make this case fail regardless of the result.
+        if !failures.is_empty() {
+            let _ = writeln!(output, "Case failed, path:{case_path:?}");
+            for failure in &failures {
+                let _ = writeln!(output, "{failure}");
+            }
+            return Ok(CaseOutcome {
+                status: CaseStatus::Failed,
+                diff: None,
+                error: Some(failures.join("\n")),
+            });
+        }
+
+        match missing_result_policy {
+            None => {}
+            Some(MissingResultPolicy::Create) => {
+                let _ = writeln!(output, "Result created, path:{result_path:?}");
+                return Ok(CaseOutcome::new(CaseStatus::ResultCreated));
+            }
+            Some(MissingResultPolicy::Fail) => {
+                let _ = writeln!(output, "Result missing, path:{result_path:?}");
+                return Ok(CaseOutcome::new(CaseStatus::ResultMissing));
+            }
+            Some(MissingResultPolicy::Warn) => {
+                let _ = writeln!(
+                    output,
+                    "Warning: result missing and ignored, path:{result_path:?}"
+                );
+                return Ok(CaseOutcome::new(CaseStatus::ResultMissingWarned));
+            }
+        }
+
+        if let Some((colored_diff, plain_diff)) = diff {
+            if self.config.result_mode == ResultMode::Bless {
+                let _ = writeln!(output, "Result updated, path:{result_path:?}");
+                let _ = writeln!(output, "{colored_diff}");
+                return Ok(CaseOutcome::new(CaseStatus::Passed));
+            }
+            let _ = writeln!(output, "Result unexpected, path:{case_path:?}");
+            let _ = writeln!(output, "{colored_diff}");
+            return Ok(CaseOutcome {
+                status: CaseStatus::Failed,
+                diff: Some(plain_diff),
+                error: None,
+            });
+        }
+
+        let _ = writeln!(
+            output,
             "Test case {:?} finished, cost: {}ms",
             path.as_os_str(),
             elapsed.as_millis()
         );
 
-        Ok(true)
+        Ok(CaseOutcome::new(CaseStatus::Passed))
+    }
+
+    /// Write new result to where [`NewResultOutput`] says, used when
+    /// `.result` files should not be modified.
+    ///
+    /// `changed` is true when the new result differs from `.result` file, or
+    /// it doesn't exist.
+    fn write_new_result(&self, result_path: &Path, new_result: &[u8], changed: bool) -> Result<()> {
+        let path = match &self.config.new_result_output {
+            NewResultOutput::Discard => return Ok(()),
+            NewResultOutput::Sibling => {
+                let path = result_path.with_extension(format!(
+                    "{}.{NEW_RESULT_EXTENSION}",
+                    self.config.result_extension
+                ));
+                if !changed {
+                    // Remove the outdated one left by previous runs.
+                    return match std::fs::remove_file(&path) {
+                        Err(e) if e.kind() != ErrorKind::NotFound => {
+                            Err(SqlnessError::WritePath { source: e, path })
+                        }
+                        _ => Ok(()),
+                    };
+                }
+                path
+            }
+            NewResultOutput::Dir(dir) => {
+                let relative = result_path
+                    .strip_prefix(&self.config.case_dir)
+                    .unwrap_or(result_path);
+                Path::new(dir).join(relative)
+            }
+        };
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        std::fs::write(&path, new_result).map_err(|e| SqlnessError::WritePath { source: e, path })
     }
 
-    async fn collect_case_paths(&self, env: &str) -> Result<Vec<PathBuf>> {
+    fn env_root(&self, env: &str) -> PathBuf {
         let mut root = PathBuf::from_str(&self.config.case_dir).unwrap();
         root.push(env);
 
+        root
+    }
+
+    /// Collect paths of cases with extensions in `splitters`.
+    async fn collect_case_paths(
+        &self,
+        env: &str,
+        splitters: &HashMap<String, SplitterRef>,
+    ) -> Result<Vec<PathBuf>> {
+        let root = self.env_root(env);
+
         let filter = Regex::new(&self.config.test_filter)?;
-        let test_case_extension = self.config.test_case_extension.as_str();
         let mut cases: Vec<_> = WalkDir::new(&root)
             .follow_links(self.config.follow_links)
             .into_iter()
@@ -210,14 +552,13 @@ impl<E: EnvController> Runner<E> {
                     .map_or(None, |entry| Some(entry.path().to_path_buf()))
                     .filter(|path| {
                         path.extension()
-                            .map(|ext| ext == test_case_extension)
-                            .unwrap_or(false)
+                            .and_then(|ext| ext.to_str())
+                            .is_some_and(|ext| splitters.contains_key(ext))
                     })
             })
-            .map(|path| path.with_extension(""))
             .filter(|path| {
                 let filename = path
-                    .file_name()
+                    .file_stem()
                     .unwrap_or_default()
                     .to_str()
                     .unwrap_or_default();
@@ -237,14 +578,476 @@ impl<E: EnvController> Runner<E> {
     }
 
     /// Compare result, return None if them are the same, else return diff changes
-    fn compare(&self, expected: &str, actual: &str) -> Option<String> {
+    /// in both colored (for terminal) and plain (for report) format.
+    fn compare(&self, expected: &str, actual: &str) -> Option<(String, String)> {
         let diff = diff_lines(expected, actual);
         let diff = diff.diff();
         let is_different = diff.iter().any(|d| !matches!(d, DiffOp::Equal(_)));
         if is_different {
-            return Some(format!("{}", SliceChangeset { diff }));
+            let plain = Self::format_plain_diff(&diff);
+            return Some((format!("{}", SliceChangeset { diff }), plain));
         }
 
         None
     }
+
+    /// Format diff changes line by line, prefixed with `-` for expected lines
+    /// and `+` for actual lines.
+    fn format_plain_diff(diff: &[DiffOp<&str>]) -> String {
+        let mut output = String::new();
+        let mut push_lines = |prefix: &str, lines: &[&str]| {
+            for line in lines {
+                output.push_str(prefix);
+                output.push_str(line);
+                output.push('\n');
+            }
+        };
+        for op in diff {
+            match op {
+                DiffOp::Equal(lines) => push_lines("  ", lines),
+                DiffOp::Remove(lines) => push_lines("- ", lines),
+                DiffOp::Insert(lines) => push_lines("+ ", lines),
+                DiffOp::Replace(old, new) => {
+                    push_lines("- ", old);
+                    push_lines("+ ", new);
+                }
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt::Display;
+    use std::path::Path;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{timer, ConfigBuilder, Database, QueryContext, QueryResult};
+
+    /// A directory removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "sqlness-runner-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            ));
+            create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, path: &str, content: &str) {
+            let path = self.0.join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        fn read(&self, path: &str) -> Option<String> {
+            std::fs::read_to_string(self.0.join(path)).ok()
+        }
+
+        fn config(&self) -> ConfigBuilder {
+            let mut builder = ConfigBuilder::default();
+            builder.case_dir(self.0.display().to_string());
+            builder
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Responds `{env}: {query}`. Queries like `SLEEP 10;` finish after given
+    /// milliseconds, and those starting with `ERROR` fail.
+    struct StubDatabase {
+        env: String,
+    }
+
+    #[async_trait]
+    impl Database for StubDatabase {
+        async fn query(&self, context: QueryContext, query: String) -> Box<dyn Display> {
+            Box::new(self.query_result(context, query).await)
+        }
+
+        async fn query_result(&self, _: QueryContext, query: String) -> QueryResult {
+            if let Some(millis) = query
+                .strip_prefix("SLEEP ")
+                .and_then(|rest| rest.trim_end_matches(';').parse().ok())
+            {
+                timer::sleep(Duration::from_millis(millis)).await;
+            }
+            let output = format!("{}: {query}", self.env);
+            if query.starts_with("ERROR") {
+                QueryResult::Error(Box::new(output))
+            } else {
+                QueryResult::Output(Box::new(output))
+            }
+        }
+    }
+
+    /// Starts [`StubDatabase`]s and records started environments.
+    #[derive(Default)]
+    struct StubController {
+        started: Mutex<Vec<String>>,
+        stopped: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl EnvController for StubController {
+        type DB = StubDatabase;
+
+        async fn start(&self, env: &str, _: Option<&Path>) -> Self::DB {
+            self.started.lock().unwrap().push(env.to_string());
+            StubDatabase {
+                env: env.to_string(),
+            }
+        }
+
+        async fn stop(&self, env: &str, _: Self::DB) {
+            self.stopped.lock().unwrap().push(env.to_string());
+        }
+    }
+
+    async fn run(config: Config) -> RunReport {
+        Runner::new(config, StubController::default())
+            .run_with_report()
+            .await
+            .unwrap()
+    }
+
+    /// Names and statuses of all cases.
+    fn statuses(report: &RunReport) -> Vec<(String, CaseStatus)> {
+        report
+            .cases()
+            .map(|(env, case)| (format!("{}/{}", env.name, case.name), case.status))
+            .collect()
+    }
+
+    /// Write cases `env/{missing,pass,stale}.sql`, where `pass` has the
+    /// expected result, `stale` has an outdated one and `missing` has none.
+    fn write_cases(dir: &TempDir) {
+        for name in ["missing", "pass", "stale"] {
+            dir.write(&format!("env/{name}.sql"), &format!("SELECT '{name}';\n"));
+        }
+        dir.write("env/pass.result", &result("pass"));
+        dir.write("env/stale.result", "SELECT 'stale';\n\nold\n\n");
+    }
+
+    /// Result of case written by [`write_cases`].
+    fn result(name: &str) -> String {
+        format!("SELECT '{name}';\n\nenv: SELECT '{name}';\n\n")
+    }
+
+    #[tokio::test]
+    async fn overwrite_result() {
+        let dir = TempDir::new();
+        write_cases(&dir);
+
+        let report = run(dir.config().build().unwrap()).await;
+        assert_eq!(
+            statuses(&report),
+            [
+                ("env/missing".to_string(), CaseStatus::ResultCreated),
+                ("env/pass".to_string(), CaseStatus::Passed),
+                ("env/stale".to_string(), CaseStatus::Failed),
+            ]
+        );
+        let stale = &report.environments[0].cases[2];
+        assert!(stale.diff.as_ref().unwrap().contains("- old\n"));
+        for name in ["missing", "pass", "stale"] {
+            assert_eq!(dir.read(&format!("env/{name}.result")), Some(result(name)));
+            assert_eq!(dir.read(&format!("env/{name}.result.new")), None);
+        }
+
+        // All results are up to date now.
+        let report = run(dir.config().build().unwrap()).await;
+        assert_eq!(report.count(CaseStatus::Passed), 3);
+    }
+
+    #[tokio::test]
+    async fn bless_result() {
+        let dir = TempDir::new();
+        write_cases(&dir);
+
+        let config = dir.config().result_mode(ResultMode::Bless).build().unwrap();
+        let report = run(config).await;
+        assert_eq!(
+            statuses(&report),
+            [
+                ("env/missing".to_string(), CaseStatus::ResultCreated),
+                ("env/pass".to_string(), CaseStatus::Passed),
+                ("env/stale".to_string(), CaseStatus::Passed),
+            ]
+        );
+        assert_eq!(dir.read("env/stale.result"), Some(result("stale")));
+        assert_eq!(dir.read("env/missing.result"), Some(result("missing")));
+    }
+
+    #[tokio::test]
+    async fn check_result_with_sibling_output() {
+        let dir = TempDir::new();
+        write_cases(&dir);
+        dir.write("env/pass.result.new", "outdated");
+
+        let config = dir
+            .config()
+            .result_mode(ResultMode::Check)
+            .new_result_output(NewResultOutput::Sibling)
+            .build()
+            .unwrap();
+        let report = run(config).await;
+        // Results are never created under check mode, `Create` is `Fail`.
+        assert_eq!(
+            statuses(&report),
+            [
+                ("env/missing".to_string(), CaseStatus::ResultMissing),
+                ("env/pass".to_string(), CaseStatus::Passed),
+                ("env/stale".to_string(), CaseStatus::Failed),
+            ]
+        );
+        assert_eq!(dir.read("env/missing.result"), None);
+        assert_eq!(
+            dir.read("env/stale.result"),
+            Some("SELECT 'stale';\n\nold\n\n".to_string())
+        );
+        // New results are only written when they differ or are missing.
+        assert_eq!(dir.read("env/missing.result.new"), Some(result("missing")));
+        assert_eq!(dir.read("env/stale.result.new"), Some(result("stale")));
+        assert_eq!(dir.read("env/pass.result.new"), None);
+    }
+
+    #[tokio::test]
+    async fn check_result_with_dir_output() {
+        let dir = TempDir::new();
+        write_cases(&dir);
+        let output = TempDir::new();
+
+        let config = dir
+            .config()
+            .result_mode(ResultMode::Check)
+            .new_result_output(NewResultOutput::Dir(output.0.display().to_string()))
+            .missing_result_policy(MissingResultPolicy::Warn)
+            .build()
+            .unwrap();
+        let report = run(config).await;
+        assert_eq!(
+            statuses(&report),
+            [
+                ("env/missing".to_string(), CaseStatus::ResultMissingWarned),
+                ("env/pass".to_string(), CaseStatus::Passed),
+                ("env/stale".to_string(), CaseStatus::Failed),
+            ]
+        );
+        for name in ["missing", "pass", "stale"] {
+            assert_eq!(
+                output.read(&format!("env/{name}.result")),
+                Some(result(name))
+            );
+            assert_eq!(dir.read(&format!("env/{name}.result.new")), None);
+        }
+        assert_eq!(dir.read("env/missing.result"), None);
+    }
+
+    #[tokio::test]
+    async fn missing_result_policy() {
+        for (policy, status) in [
+            (MissingResultPolicy::Fail, CaseStatus::ResultMissing),
+            (MissingResultPolicy::Warn, CaseStatus::ResultMissingWarned),
+        ] {
+            let dir = TempDir::new();
+            write_cases(&dir);
+
+            let config = dir.config().missing_result_policy(policy).build().unwrap();
+            let report = run(config).await;
+            assert_eq!(statuses(&report)[0], ("env/missing".to_string(), status));
+            assert_eq!(status.is_success(), policy == MissingResultPolicy::Warn);
+            assert_eq!(dir.read("env/missing.result"), None);
+            // Other cases are handled as usual.
+            assert_eq!(dir.read("env/stale.result"), Some(result("stale")));
+        }
+    }
+
+    #[tokio::test]
+    async fn run_cases_concurrently() {
+        let dir = TempDir::new();
+        // Cases finish in the reverse order of their names.
+        for (name, millis) in [("a", 150), ("b", 100), ("c", 50), ("d", 0)] {
+            dir.write(&format!("env/{name}.sql"), &format!("SLEEP {millis};\n"));
+        }
+        dir.write("env/b.result", "SLEEP 100;\n\nold\n\n");
+
+        let config = dir.config().parallelism(4).build().unwrap();
+        let start = Instant::now();
+        let report = run(config).await;
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert_eq!(
+            statuses(&report),
+            [
+                ("env/a".to_string(), CaseStatus::ResultCreated),
+                ("env/b".to_string(), CaseStatus::Failed),
+                ("env/c".to_string(), CaseStatus::ResultCreated),
+                ("env/d".to_string(), CaseStatus::ResultCreated),
+            ]
+        );
+        assert_eq!(
+            dir.read("env/a.result"),
+            Some("SLEEP 150;\n\nenv: SLEEP 150;\n\n".to_string())
+        );
+    }
+
+    /// Write environments `a`, `b` and `c` finishing in the reverse order,
+    /// where `a` and `b` have one failed case under [`ResultMode::Check`].
+    fn write_envs(dir: &TempDir) {
+        for (env, millis) in [("a", 150), ("b", 75), ("c", 0)] {
+            dir.write(&format!("{env}/case.sql"), &format!("SLEEP {millis};\n"));
+            let result = match env {
+                "c" => format!("SLEEP {millis};\n\n{env}: SLEEP {millis};\n\n"),
+                _ => "stale".to_string(),
+            };
+            dir.write(&format!("{env}/case.result"), &result);
+        }
+    }
+
+    #[tokio::test]
+    async fn run_envs_concurrently() {
+        let dir = TempDir::new();
+        write_envs(&dir);
+        let controller = StubController::default();
+        let config = dir
+            .config()
+            .env_parallelism(3)
+            .fail_fast(false)
+            .result_mode(ResultMode::Check)
+            .build()
+            .unwrap();
+        let runner = Runner::new(config, controller);
+
+        let report = runner.run_with_report().await.unwrap();
+        let names = report
+            .environments
+            .iter()
+            .map(|env| env.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        // They run concurrently, hence finish in the reverse order.
+        assert_eq!(
+            *runner.env_controller.stopped.lock().unwrap(),
+            ["c", "b", "a"]
+        );
+
+        // Errors of all failed environments are returned, in order.
+        let Err(SqlnessError::EnvRunFailed { errors }) = runner.run().await else {
+            panic!("expect EnvRunFailed");
+        };
+        let errors = errors
+            .iter()
+            .map(|(env, e)| (env.as_str(), e.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                ("a", "Run failed. 1 cases can't pass".to_string()),
+                ("b", "Run failed. 1 cases can't pass".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_fast_skips_envs() {
+        let dir = TempDir::new();
+        write_envs(&dir);
+        let config = dir
+            .config()
+            .fail_fast(true)
+            .result_mode(ResultMode::Check)
+            .build()
+            .unwrap();
+        let runner = Runner::new(config, StubController::default());
+
+        let report = runner.run_with_report().await.unwrap();
+        let envs = report
+            .environments
+            .iter()
+            .map(|env| (env.name.as_str(), env.skipped, env.cases.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(envs, [("a", false, 1), ("b", true, 0), ("c", true, 0)]);
+        assert_eq!(*runner.env_controller.started.lock().unwrap(), ["a"]);
+
+        // Only the first error is returned.
+        assert!(matches!(
+            runner.run().await,
+            Err(SqlnessError::RunFailed { count: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn report_all_envs() {
+        let dir = TempDir::new();
+        write_envs(&dir);
+        dir.write("bad/case.sql", "SELECT 1;\n");
+        dir.write("bad/config.toml", "[sqlness]\nformat = 1\n");
+        dir.write("skipped/case.sql", "SELECT 1;\n");
+        let config = dir
+            .config()
+            .fail_fast(false)
+            .result_mode(ResultMode::Check)
+            .env_filter("^(a|bad|c)$".to_string())
+            .build()
+            .unwrap();
+
+        let report = run(config).await;
+        assert!(!report.is_success());
+        assert_eq!(
+            statuses(&report),
+            [
+                ("a/case".to_string(), CaseStatus::Failed),
+                ("c/case".to_string(), CaseStatus::Passed),
+            ]
+        );
+        assert_eq!(report.count(CaseStatus::Failed), 1);
+        let envs = report
+            .environments
+            .iter()
+            .map(|env| (env.name.as_str(), env.skipped, env.is_success()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            envs,
+            [
+                ("a", false, false),
+                ("b", true, true),
+                ("bad", false, false),
+                ("c", false, true),
+                ("skipped", true, true),
+            ]
+        );
+
+        let errors = report
+            .environments
+            .into_iter()
+            .map(|env| (env.name.clone(), env.into_error()))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            errors[0],
+            (_, Some(SqlnessError::RunFailed { count: 1 }))
+        ));
+        assert!(matches!(errors[1], (_, None)));
+        assert!(matches!(
+            errors[2],
+            (_, Some(SqlnessError::InvalidEnvConfig { .. }))
+        ));
+        assert!(matches!(errors[3], (_, None)));
+        assert!(matches!(errors[4], (_, None)));
+    }
 }
@@ -0,0 +1,766 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Splitters turning case files into queries.
+//!
+//! Each case file is split by the splitter of its extension, see
+//! [`Config::splitters`]. Built-in splitters are registered in [`Registry`]
+//! under these names:
+//! - `sql`: [`SqlSplitter`], for statements ended by `;`, the default one.
+//! - `mysql`: [`SqlSplitter::mysql`], where backslashes escape in strings.
+//! - `promql`: [`BlankLineSplitter`] with `#` comments, for query languages
+//!   without delimiters like PromQL.
+//!
+//! [`Config::splitters`]: crate::Config::splitters
+
+use std::{collections::HashMap, sync::Arc};
+
+const DELIMITER: u8 = b';';
+
+pub type SplitterRef = Arc<dyn Splitter + Send + Sync>;
+
+/// Splits the content of a case file into comments and queries.
+pub trait Splitter {
+    /// Prefix of comment lines. Comments are written to the result file
+    /// before the next query, and may hold interceptors.
+    fn comment_prefix(&self) -> &str;
+
+    /// Appended to queries generated by interceptors like `TEMPLATE`, if they
+    /// don't end with it. Default: none
+    fn delimiter(&self) -> Option<&str> {
+        None
+    }
+
+    /// Split `content` into segments, in the order they appear.
+    fn split(&self, content: &str) -> Vec<Segment>;
+}
+
+/// Part of a case file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// A whole comment line.
+    Comment {
+        /// 1-based line number.
+        line: usize,
+        text: String,
+    },
+    Query(Statement),
+}
+
+/// One query of a case file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    /// 1-based number of the line this query starts on.
+    pub line: usize,
+    /// Query written to the result file.
+    pub display: String,
+    /// Query sent to the database.
+    pub execute: String,
+    /// Whether this query is properly ended, which is false for the last
+    /// query of a file missing its delimiter. How it's handled depends on
+    /// [`Config::unterminated_statement`].
+    ///
+    /// [`Config::unterminated_statement`]: crate::Config::unterminated_statement
+    pub terminated: bool,
+}
+
+/// Splitters by name.
+#[derive(Clone)]
+pub struct Registry {
+    splitters: HashMap<String, SplitterRef>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            splitters: builtin_splitters(),
+        }
+    }
+}
+
+impl Registry {
+    pub fn register(&mut self, name: &str, splitter: SplitterRef) {
+        self.splitters.insert(name.to_string(), splitter);
+    }
+
+    pub fn get(&self, name: &str) -> Option<SplitterRef> {
+        self.splitters.get(name).cloned()
+    }
+}
+
+fn builtin_splitters() -> HashMap<String, SplitterRef> {
+    [
+        (
+            SqlSplitter::NAME,
+            Arc::new(SqlSplitter::new()) as SplitterRef,
+        ),
+        (
+            SqlSplitter::MYSQL_NAME,
+            Arc::new(SqlSplitter::mysql()) as SplitterRef,
+        ),
+        (
+            BlankLineSplitter::PROMQL_NAME,
+            Arc::new(BlankLineSplitter::new("#")) as SplitterRef,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, splitter)| (name.to_string(), splitter))
+    .collect()
+}
+
+/// Splits SQL into statements ended by `;`.
+///
+/// Lines starting with `--` are comments. Delimiters don't end statements
+/// when they are in:
+/// - strings or identifiers quoted by `'`, `"` or `` ` ``, where quotes are
+///   escaped by doubling them. Backslashes only escape in PostgreSQL escape
+///   strings like `E'a\'b'`, unless created by [`SqlSplitter::mysql`],
+/// - dollar-quoted strings like `$$ ... $$` or `$body$ ... $body$`,
+/// - block comments `/* ... */`, which may be nested, and line comments `--`,
+/// - or escaped as `\;`, which is sent to the database as `;`.
+///
+/// Statement not ended by `;` at the end of file is split as unterminated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlSplitter {
+    backslash_escapes: bool,
+}
+
+impl SqlSplitter {
+    pub const NAME: &'static str = "sql";
+    pub const MYSQL_NAME: &'static str = "mysql";
+    pub const COMMENT_PREFIX: &'static str = "--";
+
+    /// Splitter of standard SQL, also used by PostgreSQL and SQLite.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Splitter of MySQL, where backslashes escape in strings quoted by `'`
+    /// or `"`, e.g. `'a\'b'`.
+    pub fn mysql() -> Self {
+        Self {
+            backslash_escapes: true,
+        }
+    }
+}
+
+impl Splitter for SqlSplitter {
+    fn comment_prefix(&self) -> &str {
+        Self::COMMENT_PREFIX
+    }
+
+    fn delimiter(&self) -> Option<&str> {
+        Some(";")
+    }
+
+    fn split(&self, content: &str) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut lexer = Lexer::new(self.backslash_escapes);
+        let mut statement: Option<Statement> = None;
+        for (index, line) in content.lines().enumerate() {
+            // Lines in a multi-line string or block comment are kept as is.
+            if lexer.is_idle() {
+                if line.starts_with(Self::COMMENT_PREFIX) {
+                    segments.push(Segment::Comment {
+                        line: index + 1,
+                        text: line.to_string(),
+                    });
+                    continue;
+                }
+                if line.is_empty() {
+                    continue;
+                }
+            }
+
+            for piece in lexer.split_line(line) {
+                let current = statement.get_or_insert_with(|| Statement {
+                    line: index + 1,
+                    display: String::new(),
+                    execute: String::new(),
+                    terminated: false,
+                });
+                current.display.push_str(&piece.display);
+                current.execute.push_str(&piece.execute);
+                if piece.terminated {
+                    current.terminated = true;
+                    segments.extend(statement.take().map(Segment::Query));
+                } else {
+                    current.display.push('\n');
+                    current.execute.push('\n');
+                }
+            }
+        }
+        // Leftovers of only comments, like an indented note at the end of
+        // file, are not a statement.
+        if let Some(mut statement) = statement.filter(|s| !is_only_comments(&s.display)) {
+            statement
+                .display
+                .truncate(statement.display.trim_end().len());
+            statement
+                .execute
+                .truncate(statement.execute.trim_end().len());
+            segments.push(Segment::Query(statement));
+        }
+
+        segments
+    }
+}
+
+/// Splits queries separated by blank lines, for query languages without
+/// delimiters like PromQL.
+///
+/// Lines starting with the comment prefix are comments, and don't end
+/// queries. The end of file ends the last query.
+#[derive(Debug, Clone)]
+pub struct BlankLineSplitter {
+    comment_prefix: String,
+}
+
+impl BlankLineSplitter {
+    pub const PROMQL_NAME: &'static str = "promql";
+
+    pub fn new(comment_prefix: &str) -> Self {
+        Self {
+            comment_prefix: comment_prefix.to_string(),
+        }
+    }
+}
+
+impl Splitter for BlankLineSplitter {
+    fn comment_prefix(&self) -> &str {
+        &self.comment_prefix
+    }
+
+    fn split(&self, content: &str) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut statement: Option<Statement> = None;
+        for (index, line) in content.lines().enumerate() {
+            if line.starts_with(&self.comment_prefix) {
+                segments.push(Segment::Comment {
+                    line: index + 1,
+                    text: line.to_string(),
+                });
+            } else if line.trim().is_empty() {
+                segments.extend(statement.take().map(Segment::Query));
+            } else if let Some(current) = &mut statement {
+                current.display.push('\n');
+                current.display.push_str(line);
+                current.execute.push('\n');
+                current.execute.push_str(line);
+            } else {
+                statement = Some(Statement {
+                    line: index + 1,
+                    display: line.to_string(),
+                    execute: line.to_string(),
+                    terminated: true,
+                });
+            }
+        }
+        segments.extend(statement.map(Segment::Query));
+
+        segments
+    }
+}
+
+/// Where the lexer is when a line ends.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum LexState {
+    #[default]
+    Normal,
+    /// In a string or identifier quoted by `'`, `"` or `` ` ``, and whether
+    /// backslashes escape in it.
+    Quoted { quote: u8, backslash_escapes: bool },
+    /// In a dollar-quoted string, ended by its tag like `$$` or `$body$`.
+    DollarQuoted(String),
+    /// In a block comment, which may be nested.
+    BlockComment(usize),
+}
+
+/// Part of a line belonging to one statement.
+#[derive(Debug, PartialEq, Eq)]
+struct Piece {
+    /// Text written to the result file.
+    display: String,
+    /// Text sent to the database, where escaped delimiters are unescaped.
+    execute: String,
+    /// Whether this piece ends the statement.
+    terminated: bool,
+}
+
+/// Lexer of [`SqlSplitter`], fed line by line.
+///
+/// Lexing is done byte by byte, which is fine as all the syntax is ASCII.
+#[derive(Debug, Default)]
+struct Lexer {
+    state: LexState,
+    /// Whether backslashes escape in all strings, not only `E'...'`.
+    backslash_escapes: bool,
+}
+
+impl Lexer {
+    fn new(backslash_escapes: bool) -> Self {
+        Self {
+            state: LexState::Normal,
+            backslash_escapes,
+        }
+    }
+
+    /// Whether the next line starts outside of strings and comments.
+    fn is_idle(&self) -> bool {
+        self.state == LexState::Normal
+    }
+
+    /// Split a line into pieces, all of them end a statement except the last
+    /// one, which continues on next line.
+    ///
+    /// Whitespace or line comment following the last delimiter of the line is
+    /// displayed along with its statement.
+    fn split_line(&mut self, line: &str) -> Vec<Piece> {
+        let bytes = line.as_bytes();
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        // Positions of backslashes escaping delimiters in current piece.
+        let mut escapes = Vec::new();
+
+        let mut i = 0;
+        while i < bytes.len() {
+            match &mut self.state {
+                LexState::Normal => match bytes[i] {
+                    quote @ (b'\'' | b'"' | b'`') => {
+                        let backslash_escapes = match quote {
+                            b'`' => false,
+                            b'\'' => self.backslash_escapes || is_escape_string(bytes, i),
+                            _ => self.backslash_escapes,
+                        };
+                        self.state = LexState::Quoted {
+                            quote,
+                            backslash_escapes,
+                        };
+                    }
+                    b'-' if bytes.get(i + 1) == Some(&b'-') => break,
+                    b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                        self.state = LexState::BlockComment(1);
+                        i += 1;
+                    }
+                    b'$' => {
+                        if let Some(tag) = dollar_tag(bytes, i) {
+                            i += tag.len() - 1;
+                            self.state = LexState::DollarQuoted(tag);
+                        }
+                    }
+                    b'\\' if bytes.get(i + 1) == Some(&DELIMITER) => {
+                        escapes.push(i);
+                        i += 1;
+                    }
+                    DELIMITER => {
+                        pieces.push(make_piece(line, start, i + 1, &escapes, true));
+                        start = i + 1;
+                        escapes.clear();
+                    }
+                    _ => {}
+                },
+                LexState::Quoted {
+                    quote,
+                    backslash_escapes,
+                } => {
+                    let quote = *quote;
+                    if bytes[i] == b'\\' && *backslash_escapes {
+                        i += 1;
+                    } else if bytes[i] == quote {
+                        if bytes.get(i + 1) == Some(&quote) {
+                            i += 1;
+                        } else {
+                            self.state = LexState::Normal;
+                        }
+                    }
+                }
+                LexState::DollarQuoted(tag) => {
+                    if bytes[i..].starts_with(tag.as_bytes()) {
+                        i += tag.len() - 1;
+                        self.state = LexState::Normal;
+                    }
+                }
+                LexState::BlockComment(depth) => {
+                    if bytes[i..].starts_with(b"/*") {
+                        *depth += 1;
+                        i += 1;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        *depth -= 1;
+                        i += 1;
+                        if *depth == 0 {
+                            self.state = LexState::Normal;
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let rest = &line[start..];
+        let trailing =
+            rest.trim().is_empty() || rest.trim_start().starts_with(SqlSplitter::COMMENT_PREFIX);
+        match pieces.last_mut() {
+            Some(last) if self.is_idle() && trailing => last.display.push_str(rest),
+            _ => pieces.push(make_piece(line, start, line.len(), &escapes, false)),
+        }
+
+        pieces
+    }
+}
+
+/// Make a piece of `line[start..end]`, removing backslashes at `escapes`.
+///
+/// Pieces not starting the line are trimmed, as they are displayed on their
+/// own lines.
+fn make_piece(line: &str, start: usize, end: usize, escapes: &[usize], terminated: bool) -> Piece {
+    let mut execute = String::with_capacity(end - start);
+    let mut last = start;
+    for &escape in escapes {
+        execute.push_str(&line[last..escape]);
+        last = escape + 1;
+    }
+    execute.push_str(&line[last..end]);
+
+    let display = &line[start..end];
+    let (display, execute) = if start > 0 {
+        (display.trim_start(), execute.trim_start())
+    } else {
+        (display, execute.as_str())
+    };
+    Piece {
+        display: display.to_string(),
+        execute: execute.to_string(),
+        terminated,
+    }
+}
+
+/// Whether `text` contains nothing but whitespace, line comments and block
+/// comments, which may be unclosed.
+fn is_only_comments(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if depth > 0 {
+            i += if bytes[i..].starts_with(b"*/") {
+                depth -= 1;
+                2
+            } else {
+                1
+            };
+        } else if bytes[i..].starts_with(b"--") {
+            i += bytes[i..].iter().take_while(|b| **b != b'\n').count();
+        } else if bytes[i].is_ascii_whitespace() {
+            i += 1;
+        } else {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether the quote at `start` begins a PostgreSQL escape string like
+/// `E'\n'`, rather than following an identifier ending with `e`.
+fn is_escape_string(bytes: &[u8], start: usize) -> bool {
+    let is_ident = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_' || *b == b'$';
+    match start.checked_sub(1).map(|i| bytes[i]) {
+        Some(b'E' | b'e') => start < 2 || !is_ident(&bytes[start - 2]),
+        _ => false,
+    }
+}
+
+/// Tag of the dollar-quoted string starting at `start`, like `$$` or `$body$`.
+///
+/// Positional parameters like `$1` and `$` in identifiers like `a$b` are not
+/// tags.
+fn dollar_tag(bytes: &[u8], start: usize) -> Option<String> {
+    let is_ident = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+    if start > 0 && (is_ident(&bytes[start - 1]) || bytes[start - 1] == b'$') {
+        return None;
+    }
+    let len = bytes[start + 1..]
+        .iter()
+        .take_while(|b| is_ident(b))
+        .count();
+    let end = start + 1 + len;
+    if bytes.get(start + 1).is_some_and(u8::is_ascii_digit) || bytes.get(end) != Some(&b'$') {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&bytes[start..=end]).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Split `text` into statements to be executed, by standard SQL lexer.
+    fn split(text: &str) -> Vec<String> {
+        split_with(Lexer::default(), text)
+    }
+
+    /// Split `text` into statements to be executed, by MySQL lexer.
+    fn split_mysql(text: &str) -> Vec<String> {
+        split_with(Lexer::new(true), text)
+    }
+
+    fn split_with(mut lexer: Lexer, text: &str) -> Vec<String> {
+        let mut statements = Vec::new();
+        let mut statement = String::new();
+        for line in text.lines() {
+            for piece in lexer.split_line(line) {
+                statement.push_str(&piece.execute);
+                if piece.terminated {
+                    statements.push(std::mem::take(&mut statement));
+                } else {
+                    statement.push('\n');
+                }
+            }
+        }
+        if !statement.trim().is_empty() {
+            statements.push(statement);
+        }
+
+        statements
+    }
+
+    #[test]
+    fn split_multiple_statements_per_line() {
+        assert_eq!(split("SELECT 1; SELECT 2;"), ["SELECT 1;", "SELECT 2;"]);
+        assert_eq!(split("SELECT 1; SELECT\n2;"), ["SELECT 1;", "SELECT\n2;"]);
+    }
+
+    #[test]
+    fn split_quoted() {
+        // Standard SQL doubles quotes.
+        assert_eq!(split("SELECT 'a;''b';"), ["SELECT 'a;''b';"]);
+        assert_eq!(
+            split("SELECT \"a;\"\"b\" FROM `t;``1`;"),
+            ["SELECT \"a;\"\"b\" FROM `t;``1`;"]
+        );
+        // MySQL also escapes them by backslash, except in backticks.
+        assert_eq!(
+            split_mysql("SELECT 'a;''b', 'c\\';d', \"e\\\";f\";"),
+            ["SELECT 'a;''b', 'c\\';d', \"e\\\";f\";"]
+        );
+        assert_eq!(
+            split_mysql("SELECT `a\\`; SELECT 1;"),
+            ["SELECT `a\\`;", "SELECT 1;"]
+        );
+        assert_eq!(
+            split("INSERT INTO t VALUES ('a;\n-- not a comment\n\nb');"),
+            ["INSERT INTO t VALUES ('a;\n-- not a comment\n\nb');"]
+        );
+    }
+
+    #[test]
+    fn split_postgresql_backslash() {
+        // Backslashes are ordinary characters in standard strings.
+        assert_eq!(
+            split("SELECT 'C:\\';\nSELECT 1;"),
+            ["SELECT 'C:\\';", "SELECT 1;"]
+        );
+        // But escape in escape strings.
+        assert_eq!(
+            split("SELECT E'a\\';b', e'\\\\'; SELECT 1;"),
+            ["SELECT E'a\\';b', e'\\\\';", "SELECT 1;"]
+        );
+        // Identifiers ending with `e` don't make escape strings.
+        assert_eq!(
+            split("SELECT 1 FROM t WHERE name='C:\\'; SELECT 1;"),
+            ["SELECT 1 FROM t WHERE name='C:\\';", "SELECT 1;"]
+        );
+        assert_eq!(
+            SqlSplitter::new().split("SELECT 'C:\\';\nSELECT 1;").len(),
+            2
+        );
+        assert_eq!(
+            SqlSplitter::mysql()
+                .split("SELECT 'C:\\\\';\nSELECT 1;")
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn split_dollar_quoted() {
+        let function =
+            "CREATE FUNCTION f() RETURNS int AS $$\nBEGIN\n  RETURN 1;\nEND;\n$$ LANGUAGE plpgsql;";
+        assert_eq!(split(function), [function]);
+        assert_eq!(
+            split("SELECT $a$ $$; $a$; SELECT 1;"),
+            ["SELECT $a$ $$; $a$;", "SELECT 1;"]
+        );
+        // Neither positional parameters nor `$` in identifiers quote.
+        assert_eq!(
+            split("SELECT $1; SELECT a$b$; SELECT 1;"),
+            ["SELECT $1;", "SELECT a$b$;", "SELECT 1;"]
+        );
+    }
+
+    #[test]
+    fn split_comments() {
+        assert_eq!(
+            split("SELECT /* a; /* b; */ c; */ 1; SELECT 2;"),
+            ["SELECT /* a; /* b; */ c; */ 1;", "SELECT 2;"]
+        );
+        assert_eq!(split("SELECT 1 /*\n;\n*/;"), ["SELECT 1 /*\n;\n*/;"]);
+        assert_eq!(split("SELECT 1 -- a; b\n;"), ["SELECT 1 -- a; b\n;"]);
+    }
+
+    #[test]
+    fn split_escaped_delimiter() {
+        assert_eq!(
+            split(r"CREATE TRIGGER t BEGIN INSERT INTO a VALUES (1)\; END;"),
+            ["CREATE TRIGGER t BEGIN INSERT INTO a VALUES (1); END;"]
+        );
+        // Not escaped in strings.
+        assert_eq!(split(r"SELECT 'a\;';"), [r"SELECT 'a\;';"]);
+    }
+
+    #[test]
+    fn display_trailing_comment() {
+        let mut lexer = Lexer::default();
+        assert_eq!(
+            lexer.split_line("SELECT 1; -- one"),
+            [Piece {
+                display: "SELECT 1; -- one".to_string(),
+                execute: "SELECT 1;".to_string(),
+                terminated: true,
+            }]
+        );
+        assert_eq!(
+            lexer.split_line("SELECT 1;  SELECT"),
+            [
+                Piece {
+                    display: "SELECT 1;".to_string(),
+                    execute: "SELECT 1;".to_string(),
+                    terminated: true,
+                },
+                Piece {
+                    display: "SELECT".to_string(),
+                    execute: "SELECT".to_string(),
+                    terminated: false,
+                },
+            ]
+        );
+        assert!(lexer.is_idle());
+        lexer.split_line("'abc");
+        assert!(!lexer.is_idle());
+    }
+
+    fn comment(line: usize, text: &str) -> Segment {
+        Segment::Comment {
+            line,
+            text: text.to_string(),
+        }
+    }
+
+    fn query(line: usize, display: &str, execute: &str) -> Segment {
+        Segment::Query(Statement {
+            line,
+            display: display.to_string(),
+            execute: execute.to_string(),
+            terminated: true,
+        })
+    }
+
+    #[test]
+    fn split_sql_case() {
+        let content = "-- SQLNESS ARG a=1\nSELECT\n-- moved before the query\n  1;\n\nSELECT '\n-- in string\n'; SELECT 2; -- two\nSELECT 3";
+        assert_eq!(
+            SqlSplitter::new().split(content),
+            [
+                comment(1, "-- SQLNESS ARG a=1"),
+                comment(3, "-- moved before the query"),
+                query(2, "SELECT\n  1;", "SELECT\n  1;"),
+                query(
+                    6,
+                    "SELECT '\n-- in string\n';",
+                    "SELECT '\n-- in string\n';"
+                ),
+                query(8, "SELECT 2; -- two", "SELECT 2;"),
+                Segment::Query(Statement {
+                    line: 9,
+                    display: "SELECT 3".to_string(),
+                    execute: "SELECT 3".to_string(),
+                    terminated: false,
+                }),
+            ]
+        );
+        assert_eq!(
+            SqlSplitter::new().split("SELECT 1;\n  \n-- trailing comment\n"),
+            [
+                query(1, "SELECT 1;", "SELECT 1;"),
+                comment(3, "-- trailing comment")
+            ]
+        );
+    }
+
+    #[test]
+    fn split_trailing_comments() {
+        let splitter = SqlSplitter::new();
+        for content in [
+            "SELECT 1;\n  -- note\n",
+            "SELECT 1;\n/* footer */\n",
+            "SELECT 1;\n/* unclosed\nfooter\n",
+            "SELECT 1;\n  /* a */ -- b\n\n  -- c",
+        ] {
+            assert_eq!(
+                splitter.split(content),
+                [query(1, "SELECT 1;", "SELECT 1;")],
+                "{content}"
+            );
+        }
+        // Code after comments is still an unterminated statement.
+        assert_eq!(
+            splitter.split("SELECT 1;\n/* footer */ SELECT 2"),
+            [
+                query(1, "SELECT 1;", "SELECT 1;"),
+                Segment::Query(Statement {
+                    line: 2,
+                    display: "/* footer */ SELECT 2".to_string(),
+                    execute: "/* footer */ SELECT 2".to_string(),
+                    terminated: false,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_by_blank_line() {
+        let content = "# SQLNESS ARG a=1\nrate(a[1m])\n\n\nsum(\n# inner comment\n  b\n)\n  \nc";
+        assert_eq!(
+            BlankLineSplitter::new("#").split(content),
+            [
+                comment(1, "# SQLNESS ARG a=1"),
+                query(2, "rate(a[1m])", "rate(a[1m])"),
+                comment(6, "# inner comment"),
+                query(5, "sum(\n  b\n)", "sum(\n  b\n)"),
+                query(10, "c", "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn builtin_registry() {
+        let registry = Registry::default();
+        let sql = registry.get(SqlSplitter::NAME).unwrap();
+        assert_eq!(sql.comment_prefix(), "--");
+        assert_eq!(sql.delimiter(), Some(";"));
+        assert_eq!(
+            registry
+                .get(SqlSplitter::MYSQL_NAME)
+                .unwrap()
+                .split(r"SELECT '\';';"),
+            [query(1, r"SELECT '\';';", r"SELECT '\';';")]
+        );
+        let promql = registry.get(BlankLineSplitter::PROMQL_NAME).unwrap();
+        assert_eq!(promql.comment_prefix(), "#");
+        assert_eq!(promql.delimiter(), None);
+        assert!(registry.get("influxql").is_none());
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 CeresDB Project Authors. Licensed under Apache-2.0.
+
+//! Runtime-agnostic timers shared by interceptors and query execution.
+//!
+//! With `tokio` or `async-std` feature enabled, timers of that runtime are used
+//! (`tokio` wins if both are enabled). Otherwise timers are driven by one
+//! background thread, so they work under any executor.
+//!
+//! Backends depending on tokio don't enable the `tokio` feature, as it would
+//! switch timers of the whole crate under feature unification.
+
+use std::future::Future;
+use std::time::Duration;
+
+/// Sleep for given duration.
+pub(crate) async fn sleep(duration: Duration) {
+    imp::sleep(duration).await
+}
+
+/// Wait for the future to complete in given duration, return `None` if it
+/// doesn't. The future is dropped on timeout.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    imp::timeout(duration, future).await
+}
+
+#[cfg(feature = "tokio")]
+mod imp {
+    use std::future::Future;
+    use std::time::Duration;
+
+    pub(super) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        tokio::time::timeout(duration, future).await.ok()
+    }
+}
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+mod imp {
+    use std::future::Future;
+    use std::time::Duration;
+
+    pub(super) async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        async_std::future::timeout(duration, future).await.ok()
+    }
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+mod imp {
+    use std::collections::BTreeMap;
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::sync::{Condvar, Mutex, OnceLock};
+    use std::task::{Context, Poll, Waker};
+    use std::time::{Duration, Instant};
+
+    use futures::future::{select, Either};
+
+    pub(super) async fn sleep(duration: Duration) {
+        Delay::new(duration).await
+    }
+
+    pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        match select(pin!(future), Delay::new(duration)).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+
+    /// Pending timers, keyed by deadline and an unique id.
+    #[derive(Default)]
+    struct Timers {
+        next_id: u64,
+        pending: BTreeMap<(Instant, u64), Waker>,
+    }
+
+    /// A background thread that wakes timers when they expire.
+    struct Driver {
+        timers: Mutex<Timers>,
+        condvar: Condvar,
+    }
+
+    impl Driver {
+        /// Get the global driver, start its thread on first call.
+        fn global() -> &'static Driver {
+            static DRIVER: OnceLock<Driver> = OnceLock::new();
+            DRIVER.get_or_init(|| {
+                std::thread::Builder::new()
+                    .name("sqlness-timer".to_string())
+                    .spawn(|| Driver::global().run())
+                    .expect("spawn timer thread");
+                Driver {
+                    timers: Mutex::new(Timers::default()),
+                    condvar: Condvar::new(),
+                }
+            })
+        }
+
+        fn run(&self) {
+            let mut timers = self.timers.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                let mut expired = Vec::new();
+                while let Some(entry) = timers.pending.first_entry() {
+                    if entry.key().0 > now {
+                        break;
+                    }
+                    expired.push(entry.remove());
+                }
+                if !expired.is_empty() {
+                    drop(timers);
+                    expired.into_iter().for_each(Waker::wake);
+                    timers = self.timers.lock().unwrap();
+                    continue;
+                }
+
+                timers = match timers.pending.keys().next() {
+                    Some((deadline, _)) => {
+                        let wait = deadline.saturating_duration_since(now);
+                        self.condvar.wait_timeout(timers, wait).unwrap().0
+                    }
+                    None => self.condvar.wait(timers).unwrap(),
+                };
+            }
+        }
+
+        fn register(&self, deadline: Instant, id: Option<u64>, waker: &Waker) -> u64 {
+            let mut timers = self.timers.lock().unwrap();
+            let id = id.unwrap_or_else(|| {
+                timers.next_id += 1;
+                timers.next_id
+            });
+            let is_earliest = timers
+                .pending
+                .keys()
+                .next()
+                .is_none_or(|first| (deadline, id) < *first);
+            timers.pending.insert((deadline, id), waker.clone());
+            if is_earliest {
+                self.condvar.notify_one();
+            }
+
+            id
+        }
+
+        fn cancel(&self, deadline: Instant, id: u64) {
+            self.timers.lock().unwrap().pending.remove(&(deadline, id));
+        }
+    }
+
+    /// A future completes after the deadline. Dropping it cancels the timer.
+    struct Delay {
+        deadline: Instant,
+        /// Id of the registered timer, `None` before first poll.
+        id: Option<u64>,
+    }
+
+    impl Delay {
+        fn new(duration: Duration) -> Self {
+            Self {
+                deadline: Instant::now() + duration,
+                id: None,
+            }
+        }
+    }
+
+    impl Future for Delay {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if Instant::now() >= self.deadline {
+                if let Some(id) = self.id.take() {
+                    Driver::global().cancel(self.deadline, id);
+                }
+                return Poll::Ready(());
+            }
+
+            let id = Driver::global().register(self.deadline, self.id, cx.waker());
+            self.id = Some(id);
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Delay {
+        fn drop(&mut self) {
+            if let Some(id) = self.id.take() {
+                Driver::global().cancel(self.deadline, id);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn cancel_on_drop() {
+            let future = std::future::pending::<()>();
+            assert!(timeout(Duration::from_millis(10), future).await.is_none());
+
+            let mut delay = Box::pin(Delay::new(Duration::from_secs(60)));
+            assert!(futures::poll!(delay.as_mut()).is_pending());
+            let key = (delay.deadline, delay.id.unwrap());
+            assert!(Driver::global()
+                .timers
+                .lock()
+                .unwrap()
+                .pending
+                .contains_key(&key));
+            drop(delay);
+            assert!(!Driver::global()
+                .timers
+                .lock()
+                .unwrap()
+                .pending
+                .contains_key(&key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sleep_and_timeout() {
+        let now = Instant::now();
+        sleep(Duration::from_millis(100)).await;
+        assert!(now.elapsed() >= Duration::from_millis(100));
+
+        let output = timeout(Duration::from_secs(10), async { 1 }).await;
+        assert_eq!(output, Some(1));
+
+        let output = timeout(Duration::from_millis(10), sleep(Duration::from_secs(10))).await;
+        assert_eq!(output, None);
+    }
+}